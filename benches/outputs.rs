@@ -0,0 +1,77 @@
+// Compares a run computing every output with a run restricted to `--outputs all_changed_files` on a
+// repository with many changed files. Run with `cargo bench --bench outputs`.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use git2::{Repository, Signature};
+
+// Number of files changed between the two commits
+const CHANGED_FILES: usize = 20_000;
+
+// Number of runs of each configuration, the fastest one is reported
+const RUNS: usize = 5;
+
+// Utility function to create a repository whose last commit modifies every file of the first commit
+fn create_repository(dir: &Path) {
+    let _ = fs::remove_dir_all(dir);
+    fs::create_dir_all(dir).unwrap();
+
+    let repo = Repository::init(dir).unwrap();
+    let signature = Signature::now("Bench", "bench@example.com").unwrap();
+
+    for revision in ["first", "second"] {
+        let mut index = repo.index().unwrap();
+        for file in 0..CHANGED_FILES {
+            let path = format!("dir{}/file{}.txt", file % 100, file);
+            fs::create_dir_all(dir.join(format!("dir{}", file % 100))).unwrap();
+            fs::write(dir.join(&path), format!("{} {}\n", revision, file)).unwrap();
+            index.add_path(Path::new(&path)).unwrap();
+        }
+        index.write().unwrap();
+
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &signature, &signature, revision, &tree, &parents).unwrap();
+    }
+}
+
+// Utility function to time the fastest of `RUNS` runs of the binary in the repository
+fn time_runs(dir: &Path, extra_args: &[&str]) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            let output = Command::new(env!("CARGO_BIN_EXE_changed_files"))
+                .current_dir(dir)
+                .env_remove("GITHUB_ACTIONS")
+                .args(["--files", "", "--files-from-source-file", "", "--files-ignore", "", "--files-ignore-from-source-file", ""])
+                .args(["--since", "", "--until", "", "--diff-relative", "", "--dir-names-max-depth", ""])
+                .args(["--base-sha", "HEAD~1", "--quiet"])
+                .args(extra_args)
+                .output()
+                .unwrap();
+            let elapsed = start.elapsed();
+
+            assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+            elapsed
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let dir: PathBuf = std::env::temp_dir().join(format!("changed-files-bench-{}", std::process::id()));
+    create_repository(&dir);
+
+    let all_outputs = time_runs(&dir, &[]);
+    let all_changed_files = time_runs(&dir, &["--outputs", "all_changed_files"]);
+
+    println!("{} changed files, fastest of {} runs", CHANGED_FILES, RUNS);
+    println!("outputs: all                {:>8.1} ms", all_outputs.as_secs_f64() * 1000.0);
+    println!("outputs: all_changed_files  {:>8.1} ms", all_changed_files.as_secs_f64() * 1000.0);
+    println!("speedup                     {:>8.2}x", all_outputs.as_secs_f64() / all_changed_files.as_secs_f64());
+
+    let _ = fs::remove_dir_all(&dir);
+}
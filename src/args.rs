@@ -1,7 +1,12 @@
 use clap::{Parser, ValueEnum};
+use serde::Serialize;
 use std::fmt;
+use std::fs;
 
-#[derive(Parser, Debug)]
+use crate::error::{self, FailureReason};
+use crate::github_output;
+
+#[derive(Parser, Debug, Serialize)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
     /// Split character for output strings
@@ -17,51 +22,51 @@ pub struct Args {
     pub old_new_separator: String,
 
     /// Split character for old and new renamed filename pairs.
-    #[clap(short, long, default_value = " ")]
+    #[clap(long, default_value = " ")]
     pub old_new_files_separator: String,
 
-    /// File and directory patterns to detect changes using only these list of file(s) (Defaults to the entire repo) **NOTE:** Multiline file/directory patterns should not include quotes.
+    /// File and directory patterns to detect changes using only these list of file(s) (Defaults to the entire repo) **NOTE:** Multiline file/directory patterns should not include quotes. Use `@<path>` to read newline separated patterns from a file outside of the repository e.g `@$RUNNER_TEMP/files.txt`.
     #[clap(short, long)]
     pub files: String,
 
     /// Separator used to split the `files` input
-    #[clap(short, long, default_value = "\n")]
+    #[clap(long, default_value = "\n")]
     pub files_separator: String,
 
-    /// Source file(s) used to populate the `files` input.
-    #[clap(short, long)]
+    /// Source file(s) used to populate the `files` input. Use `@<path>` to read the newline separated list from a file.
+    #[clap(long)]
     pub files_from_source_file: String,
 
     /// Separator used to split the `files_from_source_file` input
-    #[clap(short, long, default_value = "\n")]
+    #[clap(long, default_value = "\n")]
     pub files_from_source_file_separator: String,
 
-    /// Ignore changes to these file(s) **NOTE:** Multiline file/directory patterns should not include quotes.
-    #[clap(short, long)]
+    /// Ignore changes to these file(s) **NOTE:** Multiline file/directory patterns should not include quotes. Use `@<path>` to read newline separated patterns from a file.
+    #[clap(long)]
     pub files_ignore: String,
 
     /// Separator used to split the `files_ignore` input
-    #[clap(short, long, default_value = "\n")]
+    #[clap(long, default_value = "\n")]
     pub files_ignore_separator: String,
 
-    /// Source file(s) used to populate the `files_ignore` input
-    #[clap(short, long)]
+    /// Source file(s) used to populate the `files_ignore` input. Use `@<path>` to read the newline separated list from a file.
+    #[clap(long)]
     pub files_ignore_from_source_file: String,
 
     /// Separator used to split the `files_ignore_from_source_file` input
-    #[clap(short, long, default_value = "\n")]
+    #[clap(long, default_value = "\n")]
     pub files_ignore_from_source_file_separator: String,
 
     /// Specify a different commit SHA used for comparing changes
-    #[clap(short, long)]
+    #[clap(long, default_value = "")]
     pub sha: String,
 
-    /// Specify a different base commit SHA used for comparing changes
-    #[clap(short, long)]
+    /// Specify a different base commit SHA used for comparing changes. The base inputs take precedence in the order `base_sha`, `base_ref`, `since`, `since_last_remote_commit` and then the event, the one used is output as `base_strategy`.
+    #[clap(short, long, default_value = "")]
     pub base_sha: String,
 
     /// Get changed files for commits whose timestamp is older than the given time.
-    #[clap(short, long)]
+    #[clap(long)]
     pub since: String,
 
     /// Get changed files for commits whose timestamp is earlier than the given time.
@@ -76,20 +81,20 @@ pub struct Args {
     #[clap(short, long, default_value = "true")]
     pub quotepath: String,
 
-    /// Exclude changes outside the current directory and show path names relative to it. **NOTE:** This requires you to specify the top level directory via the `path` input.
+    /// Exclude changes outside the given directory of the repository and show path names relative to it like `git diff --relative`. **NOTE:** This requires you to specify the top level directory via the `path` input.
     #[clap(short, long)]
     pub diff_relative: String,
 
     /// Output unique changed directories instead of filenames. **NOTE:** This returns `.` for changed files located in the root of the project.
-    #[clap(short, long, default_value = "false")]
+    #[clap(long, default_value = "false")]
     pub dir_names: bool,
 
-    /// Maximum depth of directories to output. e.g `test/test1/test2` with max depth of `2` returns `test/test1`.
-    #[clap(short, long)]
+    /// Maximum depth of directories to output. e.g `test/test1/test2` with max depth of `2` returns `test/test1`. `0` or an empty value outputs the full directories.
+    #[clap(long)]
     pub dir_names_max_depth: String,
 
     /// Exclude the root directory represented by `.` from the output when `dir_names`is set to `true`.
-    #[clap(short, long, default_value = "false")]
+    #[clap(long, default_value = "false")]
     pub dir_names_exclude_root: bool,
 
     /// Output list of changed files in a JSON formatted string which can be used for matrix jobs.
@@ -97,15 +102,19 @@ pub struct Args {
     pub json: bool,
 
     /// Output list of changed files in [jq](https://devdocs.io/jq/) raw output format which means that the output will not be surrounded by quotes and special characters will not be escaped.
-    #[clap(short, long, default_value = "false")]
+    #[clap(long, default_value = "false")]
     pub json_raw_format: bool,
 
-    /// Depth of additional branch history fetched. **NOTE**: This can be adjusted to resolve errors with insufficient history.
-    #[clap(short, long, default_value = "50")]
+    /// Depth of additional branch history fetched. Set to `0` to fetch the full history. **NOTE**: This can be adjusted to resolve errors with insufficient history.
+    #[clap(long, default_value = "50")]
     pub fetch_depth: u32,
 
+    /// Fetch the full branch history, equivalent to setting `fetch_depth` to `0`.
+    #[clap(long, default_value = "false")]
+    pub fetch_full_history: bool,
+
     /// Use the last commit on the remote branch as the `base_sha`. Defaults to the last non merge commit on the target branch for pull request events and the previous remote commit of the current branch for push events.
-    #[clap(short, long, default_value = "false")]
+    #[clap(long, default_value = "false")]
     pub since_last_remote_commit: bool,
 
     /// Write outputs to files in the `.github/outputs` folder by default.
@@ -113,10 +122,450 @@ pub struct Args {
     pub write_output_files: bool,
 
     /// Directory to store output files.
-    #[clap(short, long, default_value = ".github/outputs")]
+    #[clap(long, default_value = ".github/outputs")]
     pub output_dir: String,
 
     /// Indicates whether to include match directories
     #[clap(short, long, default_value = "true")]
     pub match_directories: bool,
+
+    /// Path to the CODEOWNERS file used to determine the owners of the changed files. Defaults to `.github/CODEOWNERS`, `CODEOWNERS` or `docs/CODEOWNERS` when present.
+    #[clap(long, default_value = "")]
+    pub codeowners: String,
+
+    /// Group the changed files by their lowercase file extension. Outputs `changed_extensions` and with `json` set to `true` a `changed_files_by_extension` mapping.
+    #[clap(long, default_value = "false", conflicts_with = "dir_names")]
+    pub group_by_extension: bool,
+
+    /// Prefix prepended to every output key e.g `docs_` outputs `docs_owners`, wherever the outputs are written: GITHUB_OUTPUT, the dotenv file or stdout (upper-cased), the files of `max_github_output_bytes` and the files of the filter sets. Must be a valid identifier.
+    #[clap(long, default_value = "")]
+    pub output_prefix: String,
+
+    /// Fail when no changes are detected between the previous and current commit.
+    #[clap(long, default_value = "false")]
+    pub fail_on_no_changes: bool,
+
+    /// Exclude changes to files located in the root of the repository from all outputs. This is applied after the `files` patterns.
+    #[clap(long, default_value = "false")]
+    pub exclude_root_level_files: bool,
+
+    /// Maximum number of `files` and `files_ignore` glob patterns.
+    #[clap(long, default_value = "10000")]
+    pub max_patterns: usize,
+
+    /// Ignore `git replace` objects in every git command, matching libgit2 which never applies replace refs.
+    #[clap(long, default_value = "false")]
+    pub no_replace_objects: bool,
+
+    /// Use the checked out HEAD as the current commit for push events instead of `GITHUB_SHA`.
+    #[clap(long, default_value = "false")]
+    pub use_head: bool,
+
+    /// Use the `after` commit of the push event (`github.event.after`) as the current commit even when the branch has since moved. Combined with `since_last_remote_commit` this reproduces exactly the commits of that push, e.g when re-running an outdated workflow.
+    #[clap(long, default_value = "false", conflicts_with = "use_head")]
+    pub use_event_after: bool,
+
+    /// Print the effective configuration as JSON and exit without running any git commands.
+    #[clap(long, default_value = "false")]
+    #[serde(skip)]
+    pub print_config: bool,
+
+    /// Print the JSON Schema of the `single_output: json` report and exit without running any git commands.
+    #[clap(long, default_value = "false")]
+    #[serde(skip)]
+    pub print_schema: bool,
+
+    /// Compare the changed files of two reports saved from the `single_output: json` result e.g before and after a rebase and exit without running any git commands. Prints the files only changed in the new report (`+`), only changed in the old report (`-`) and the files whose status changed (`~`), as JSON when `json` is `true`. Exits with `1` when the reports differ.
+    #[clap(long, num_args = 2, value_names = ["OLD", "NEW"])]
+    #[serde(skip)]
+    pub compare_reports: Vec<String>,
+
+    /// Include every ancestor directory of the changed files when `dir_names` is set to `true` e.g `a/b/c.txt` outputs `a` and `a/b`.
+    #[clap(long, default_value = "false")]
+    pub dir_names_include_ancestors: bool,
+
+    /// Recover the deleted files from the previous commit.
+    #[clap(long, default_value = "false")]
+    pub recover_deleted_files: bool,
+
+    /// Directory, relative to the repository, to restore the deleted files to. Defaults to the repository root.
+    #[clap(long, default_value = "")]
+    pub recover_deleted_files_to_destination: String,
+
+    /// Additional comparison ranges as a JSON list of `{"name": ..., "base": ..., "head": ..., "diff_mode": ...}`. `head` defaults to the current commit and `diff_mode` (`..` or `...`) to the diff used for the event. Each range outputs `<name>_any_changed`, `<name>_all_changed_files`, `<name>_all_modified_files`, `<name>_added_files`, `<name>_deleted_files`, `<name>_modified_files` and `<name>_renamed_files`.
+    #[clap(long, default_value = "")]
+    pub ranges: String,
+
+    /// Ignore modified files whose contents are identical once line endings are normalized e.g after a CRLF renormalization. The ignored files are output as `filter_only_changed_files`. This reads the content of every modified file.
+    #[clap(long, default_value = "false")]
+    pub ignore_filter_only_changes: bool,
+
+    /// Fail when the previous and current commit of a pull request have unrelated histories e.g after the history was rewritten.
+    #[clap(long, default_value = "false")]
+    pub fail_on_unrelated_histories: bool,
+
+    /// Base branch, tag or any other revision used for comparing changes instead of the one derived from the event, compared with `sha` (defaults to the current commit) using a two dot diff. With `pull_request_number` it's the base branch of the pull request.
+    #[clap(long, default_value = "")]
+    pub base_ref: String,
+
+    /// Emit a notice for every added or modified file larger than this number of bytes and output them as `large_changed_files`. `0` disables it.
+    #[clap(long, default_value = "0")]
+    pub annotate_large_files_bytes: u64,
+
+    /// Output the changed files of every commit in the range as `per_commit_changed_files`, a JSON list of `{"sha": ..., "files": [...]}` ordered from the oldest commit. Requires `json` to be set to `true`.
+    #[clap(long, default_value = "false")]
+    pub per_commit_files: bool,
+
+    /// Maximum number of commits included in `per_commit_changed_files`, the newest commits are dropped.
+    #[clap(long, default_value = "250")]
+    pub max_commits: usize,
+
+    /// Use the combined diff (files changed compared to every parent) for merge commits in `per_commit_changed_files` instead of the diff to the first parent.
+    #[clap(long, default_value = "false")]
+    pub per_commit_full_merge_diff: bool,
+
+    /// GitHub token used to authenticate git fetches. Defaults to the `GITHUB_TOKEN` or `GH_TOKEN` environment variable.
+    #[clap(long, default_value = "")]
+    #[serde(skip)]
+    pub token: SecretString,
+
+    /// Seconds to keep retrying when the repository or a git command is blocked by a lock file held by another process e.g `index.lock`. `0` disables retrying.
+    #[clap(long, default_value = "0")]
+    pub lock_retry_seconds: u64,
+
+    /// Comma or newline separated list of the outputs to compute and write e.g `all_changed_files,any_changed`. Defaults to `all`.
+    #[clap(long, default_value = "all")]
+    pub outputs: String,
+
+    /// Maximum number of files written to the `other_changed_files` output (changed files which don't match the `files` patterns). Larger lists only output `other_changed_files_count` and `any_other_changed` unless `other_changed_files` is listed in `outputs`.
+    #[clap(long, default_value = "1000")]
+    pub other_changed_files_max: usize,
+
+    /// Output naming: `node` uses the output names of the Node.js action, `native` uses this crate's names e.g `renamed_file_pairs` instead of `all_old_new_renamed_files` and `has_changes` instead of `any_changed`, with JSON booleans in `effective_config`.
+    #[clap(long, value_enum, default_value = "node")]
+    pub output_compat: OutputCompat,
+
+    /// Where the outputs are written: `github` writes them to GITHUB_OUTPUT, `dotenv` appends `KEY="value"` lines to `output_dotenv_file` for CI systems reading dotenv files e.g GitLab CI and `stdout` prints the same lines. The keys are upper-cased and newlines in the values are written as `\n`.
+    #[clap(long, value_enum, default_value = "github")]
+    pub output_target: OutputTarget,
+
+    /// The dotenv file the outputs are appended to with `output_target: dotenv`.
+    #[clap(long, default_value = "")]
+    pub output_dotenv_file: String,
+
+    /// Use the checked out HEAD as the current commit when it doesn't match the event (`checkout_mismatch`). By default the event commit (`GITHUB_SHA` or the pull request head) wins.
+    #[clap(long, default_value = "false")]
+    pub trust_checkout: bool,
+
+    /// Get the changed files of a pull request by number e.g in a `workflow_dispatch` job, as if the workflow was triggered by the pull request. Requires `base_ref` (the base branch) or `base_sha`.
+    #[clap(long, default_value = "")]
+    pub pull_request_number: String,
+
+    /// Directory of pattern files, each evaluated as a named filter set against the same changed files. The outputs of a filter set are prefixed with the file stem e.g `docs_any_changed` for `docs.txt`, with `write_output_files` they're written to `<output_dir>/<file stem>/<output_prefix><output>.txt`.
+    #[clap(long, default_value = "")]
+    pub filters_from_files: String,
+
+    /// Compare two tags regardless of the event e.g `v1.4.0..v1.5.0` for release notes. The tags are fetched when missing and compared with a two dot diff, the tag names are output as `base_tag` and `head_tag`.
+    #[clap(long, default_value = "", conflicts_with_all = ["base_sha", "base_ref", "sha", "pull_request_number"])]
+    pub between_tags: String,
+
+    /// Compare the submodule `url`, `branch` and `path` settings of `.gitmodules` between the commits. Outputs `submodule_config_changed` and `submodule_config_changes`, a JSON object of the changed settings by submodule name.
+    #[clap(long, default_value = "false")]
+    pub submodule_config_changes: bool,
+
+    /// File used to persist the resolved commits, later invocations in the same job with the same event, HEAD and inputs skip fetching and resolving the commits. Defaults to `<output_dir>/.changes-state.json` when `write_output_files` is set.
+    #[clap(long, default_value = "")]
+    pub state_file: String,
+
+    /// Only print warnings, errors and the outputs. Progress messages, `::debug::` messages and the log group are not printed, so a successful run outside of GitHub Actions only prints the outputs.
+    #[clap(long, default_value = "false")]
+    pub quiet: bool,
+
+    /// Print the `::debug::` messages as plain lines, GitHub Actions only shows them with step debug logging enabled. Can't be combined with `quiet`.
+    #[clap(long, default_value = "false", conflicts_with = "quiet")]
+    pub verbose: bool,
+
+    /// Output `pattern_match_counts`, a JSON object of the number of changed files matched by each `files` pattern. A file matching several patterns is counted for each of them.
+    #[clap(long, default_value = "false")]
+    pub output_pattern_stats: bool,
+
+    /// Exclude the changed files marked as generated in the committed `.gitattributes` files (`linguist-generated` by default, see `generated_attribute_name`). The excluded files are output as `generated_changed_files`.
+    #[clap(long, default_value = "false")]
+    pub exclude_generated_files: bool,
+
+    /// The `.gitattributes` attribute marking generated files for `exclude_generated_files`.
+    #[clap(long, default_value = "linguist-generated")]
+    pub generated_attribute_name: String,
+
+    /// Output the duration of each phase in milliseconds: `fetch_duration_ms`, `sha_resolution_duration_ms`, `diff_duration_ms`, `output_write_duration_ms` and `total_duration_ms`. The durations are always printed as debug messages.
+    #[clap(long, default_value = "false")]
+    pub output_timings: bool,
+
+    /// Count the commits changing each matched file: `file_churn` is a JSON object of the number of commits by path and `high_churn_files` lists the files changed by at least `churn_threshold` commits. Uses the per commit diffs (see `max_commits`) and requires `json` to be set to `true`.
+    #[clap(long, default_value = "false")]
+    pub output_churn: bool,
+
+    /// Minimum number of commits changing a file for `high_churn_files`.
+    #[clap(long, default_value = "3")]
+    pub churn_threshold: usize,
+
+    /// Set to `json` to write a single `result` output instead of the individual outputs: a JSON document with the resolved commits, the flags, counts, file lists and other outputs, and the outputs of every range and filter set under `groups`. Read it with `fromJSON(steps.<id>.outputs.result)`.
+    #[clap(long, default_value = "")]
+    pub single_output: String,
+
+    /// Output `match_provenance`, a JSON object keyed by changed file with the `files` patterns which matched it (`matched_patterns`, at most 10 followed by a `+N more` marker) and the pattern which ignored it (`ignored_by`). Requires `json`.
+    #[clap(long, default_value = "false", requires = "json")]
+    pub include_match_provenance: bool,
+
+    /// Fetch the history of the submodules recursively with the history of the repository. By default submodules are not fetched recursively, the changed files within a submodule only need the submodule commits fetched separately.
+    #[clap(long, default_value = "false")]
+    pub fetch_submodule_history: bool,
+
+    /// Security sensitive file patterns added to the built-in patterns (`.github/workflows/**`, `.github/actions/**` and `**/CODEOWNERS`) for the `security_sensitive_files_changed` output, one pattern per line. The output is computed before the `files` and `files_ignore` filters so they can't suppress it.
+    #[clap(long, default_value = "")]
+    pub security_sensitive_patterns: String,
+
+    /// Don't include the built-in patterns in the `security_sensitive_patterns`.
+    #[clap(long, default_value = "false")]
+    pub no_builtin_sensitive_patterns: bool,
+
+    /// Directory, relative to the repository, to write the complete file listings of the previous and the current commit to: `base_tree.jsonl` and `head_tree.jsonl` with one `{"path", "oid", "mode"}` object per line. Their paths are output as `base_tree_file` and `head_tree_file`.
+    #[clap(long, default_value = "")]
+    pub dump_trees: String,
+
+    /// Only write the files matching the `files` patterns to the `dump_trees` listings.
+    #[clap(long, default_value = "false")]
+    pub dump_trees_filtered: bool,
+
+    /// Index of the shard of the changed files to output, from `0` to `shard_count - 1`. Files are assigned to a shard by a stable hash of their path, so parallel jobs with the same inputs process disjoint sets of files.
+    #[clap(long, default_value = "0")]
+    pub shard_index: u32,
+
+    /// Number of shards the changed files are split into, `1` disables sharding.
+    #[clap(long, default_value = "1")]
+    pub shard_count: u32,
+
+    /// Ignore the changed files matching a curated list of patterns e.g lockfiles, `dist` directories and minified files. Negated `files_ignore` patterns e.g `!**/dist/**` re-include files. The patterns are output as `default_ignores_applied`.
+    #[clap(long, default_value = "false")]
+    pub use_default_ignores: bool,
+
+    /// Path of a JSON file with the event payload, used instead of `GITHUB_EVENT_PATH` e.g to simulate an event locally. Its fields take precedence over the environment variables.
+    #[clap(long, default_value = "")]
+    pub event_json: String,
+
+    /// The ref of the event, overrides `GITHUB_REF` and the event payload.
+    #[clap(long = "ref", default_value = "")]
+    pub event_ref: String,
+
+    /// The ref name of the event, overrides `GITHUB_REF_NAME`.
+    #[clap(long, default_value = "")]
+    pub ref_name: String,
+
+    /// The `before` commit of a push event, overrides `GITHUB_EVENT_BEFORE` and the event payload.
+    #[clap(long, default_value = "")]
+    pub before: String,
+
+    /// Whether the push event was forced (`true` or `false`), overrides `GITHUB_EVENT_FORCED` and the event payload.
+    #[clap(long, default_value = "")]
+    pub forced: String,
+
+    /// Maximum number of deltas (changed files) of the diff, `0` is unlimited. Above the limit only `any_changed`, `all_changed_and_modified_files_count` and `deltas_limit_exceeded` are output, the file lists and every feature reading the changed files are skipped. The ranges and the per commit diffs follow the same limit.
+    #[clap(long, default_value = "0")]
+    pub max_deltas: u64,
+
+    /// Compare against the default branch of the repository on any event: the merge base of the current commit and the tip of the default branch is used as the previous commit. The default branch is read from the event payload (`repository.default_branch`) or `refs/remotes/origin/HEAD` and output as `default_branch`. On the default branch itself the event is used as usual.
+    #[clap(long, default_value = "false")]
+    pub base_default_branch: bool,
+
+    /// Compare a literal range regardless of the event e.g `origin/main...HEAD`. A two dot range compares the commits directly, a three dot range compares the head with the merge base. An empty side is `HEAD` like with git.
+    #[clap(long, default_value = "", conflicts_with_all = ["base_sha", "base_ref", "sha", "between_tags", "pull_request_number"])]
+    pub range: String,
+
+    /// Rules for files which have to change together as a YAML list e.g `[{if: [src/api/**], then: [docs/api/**]}]`. When a changed file matches an `if` pattern but no changed file matches a `then` pattern the run fails, the violated rules are output as `co_change_violations`.
+    #[clap(long, default_value = "")]
+    pub require_co_changes: String,
+
+    /// Only warn about the `require_co_changes` violations instead of failing.
+    #[clap(long, default_value = "false")]
+    pub co_change_warn_only: bool,
+
+    /// Treat every warning as an error: the warning is printed as an `::error::` annotation and the action fails with `failure_reason` set to `strict_warning`. The outputs written before the warning are kept.
+    #[clap(long, default_value = "false")]
+    pub strict: bool,
+
+    /// Count only the first parent of each commit for `commits_count` and `behind_count`, the commits merged in by merge commits aren't counted.
+    #[clap(long, default_value = "false")]
+    pub count_first_parent_only: bool,
+
+    /// Maximum number of commits counted for `commits_count` and `behind_count`, `commits_count_exceeded` is `true` when a count stops at the limit. `0` is unlimited.
+    #[clap(long, default_value = "10000")]
+    pub max_commits_count: usize,
+
+    /// Newline separated literal path prefixes e.g `third_party/` left out of the diff by libgit2, no file beneath them is reported in any output. Unlike `files_ignore` the files are never diffed, which is faster for large directories.
+    #[clap(long, default_value = "")]
+    pub prune_paths: String,
+
+    /// Match the `files` patterns and the `prune_paths` case-sensitively. Set to `false` to match regardless of the case e.g on macOS runners, the libgit2 pathspecs follow this input instead of `core.ignorecase`.
+    #[clap(long, default_value = "true", action = clap::ArgAction::Set)]
+    pub files_match_case: bool,
+
+    /// Report the path of a submodule whose commit changed as an added, deleted or modified file e.g `vendor/lib`, matched by the `files` patterns like any other file. The files changed within the submodule are listed separately when the submodule commits are available, see `fetch_submodule_history`.
+    #[clap(long, default_value = "true", action = clap::ArgAction::Set)]
+    pub include_submodule_paths: bool,
+
+    /// Exit on the first problem with the inputs. Set to `false` to check every input (patterns, ranges, co-change rules, conflicting inputs) and report all the problems together before exiting, problems while fetching or resolving the commits still exit immediately.
+    #[clap(long, default_value = "true", action = clap::ArgAction::Set)]
+    pub fail_fast: bool,
+
+    /// Record the run into this directory to debug how the commits were resolved: the event payload, the GitHub environment variables, the output of every git command and the resolved commits. The token, credentials in URLs and the values of `record_redact` are removed.
+    #[clap(long, default_value = "")]
+    pub record: String,
+
+    /// Values replaced by `<redacted-N>` in the recording e.g branch names, one value per line.
+    #[clap(long, default_value = "")]
+    pub record_redact: String,
+
+    /// Replay a recording of `record` from this directory: the recorded environment variables and event payload are restored and every git command returns its recorded output instead of running, then the resolved commits are printed and compared with the recorded ones. The checkout must contain the recorded commits.
+    #[clap(long, default_value = "", conflicts_with = "record")]
+    pub replay: String,
+
+    /// Warn when a fetch moved a remote-tracking ref e.g `refs/remotes/origin/main` that existed before the run, the fetches force update the refs so other steps of the job may have seen a different commit. The moved refs are always output as `refs_updated`.
+    #[clap(long, default_value = "false")]
+    pub warn_on_ref_updates: bool,
+
+    /// Only keep the changed files whose size changed by at least this number of bytes, the other changed files are output as `small_changed_files`. An added file counts its full size and a deleted file its full previous size. The size of LFS files is the size of the pointer file, not the declared size. `0` disables it.
+    #[clap(long, default_value = "0")]
+    pub min_size_delta_bytes: u64,
+
+    /// Follow the renames through every commit of the range and output `rename_chains`, a JSON object of every path of a renamed file from the oldest e.g `{"c": ["a", "b", "c"]}`. Each commit is diffed against its first parent with rename detection, so a file renamed twice or changed a lot between the renames is still followed. Falls back to the renames between the previous and the current commit when the range has more than `max_commits` commits.
+    #[clap(long, default_value = "false")]
+    pub track_renames_through_history: bool,
+
+    /// Merge the previous commit (e.g the tip of the pull request base branch) into the current commit in memory and output the conflicting files matching the `files` patterns as `unmerged_files`. The working tree and the index aren't changed. Without it `unmerged_files` is empty since a diff between commits has no conflicts.
+    #[clap(long, default_value = "false")]
+    pub detect_conflicts_with_base: bool,
+
+    /// Directory of the temporary files e.g the `.gitmodules` of the compared commits, each run uses its own subdirectory which is removed once the run succeeds. Defaults to `RUNNER_TEMP` and then the system temporary directory.
+    #[clap(long, default_value = "")]
+    pub temp_dir: String,
+
+    /// Keep the temporary files after a successful run, their directory is printed. They are always kept when the run fails.
+    #[clap(long, default_value = "false")]
+    pub keep_temp: bool,
+
+    /// Only keep the changed files whose path has at most this number of components e.g `2` keeps `plugins/a.txt` but not `plugins/a/b.txt`. The number of dropped files is output as `deep_changed_files_count`. Components are counted like `dir_names_max_depth`, which counts the directories only. `0` disables it.
+    #[clap(long, default_value = "0")]
+    pub max_path_depth: usize,
+
+    /// Output the GitHub web URLs of the changed files as `changed_files_urls`, a JSON object of `url` and `diff_url` by path. Requires GITHUB_SERVER_URL and GITHUB_REPOSITORY.
+    #[clap(long, default_value = "false")]
+    pub include_urls: bool,
+
+    /// Largest output written to GITHUB_OUTPUT in bytes, GitHub truncates or rejects larger outputs. A larger output is replaced by `<output>_output_too_large` and written to `output_dir`, its path is output as `<output>_file`. `0` disables the check.
+    #[clap(long, default_value = "1048576")]
+    pub max_github_output_bytes: usize,
+
+    /// Never run git commands e.g in containers that deny spawning processes. Commits are resolved with libgit2 only, which requires the full history in the checkout. `since` and `until` only accept ISO-8601 dates and the git version check is skipped.
+    #[clap(long, default_value = "false")]
+    pub no_subprocess: bool,
+
+    /// Output the number of added, modified and deleted files by directory as `dir_stats` e.g `{"src": {"added": 2, "modified": 5, "deleted": 0}}`. Files at the root are counted under `.`. Requires `json`.
+    #[clap(long, default_value = "false")]
+    pub output_dir_stats: bool,
+
+    /// Number of directories of the `dir_stats` keys, counted like `dir_names_max_depth`.
+    #[clap(long, default_value = "1")]
+    pub dir_stats_depth: usize,
+}
+
+impl Args {
+    // The resolved configuration used for the run, booleans given as strings are normalized
+    pub fn effective_config(&self) -> serde_json::Value {
+        let mut config = serde_json::to_value(self).unwrap();
+        config["quotepath"] = serde_json::Value::Bool(self.quotepath != "false");
+        config["forced"] = github_output::format_json_boolean(&self.forced, &self.output_compat);
+
+        if self.fetch_full_history {
+            config["fetch_depth"] = serde_json::Value::from(0);
+        }
+
+        config
+    }
+
+    // Replace the `@<path>` values of the list inputs with the entries of the file joined with the separator
+    // of the input, so long lists don't have to be passed as an argument which could exceed the argv limit
+    pub fn resolve_file_references(&mut self) {
+        self.files = read_file_reference(&self.files, &self.files_separator);
+        self.files_from_source_file = read_file_reference(&self.files_from_source_file, &self.files_from_source_file_separator);
+        self.files_ignore = read_file_reference(&self.files_ignore, &self.files_ignore_separator);
+        self.files_ignore_from_source_file = read_file_reference(
+            &self.files_ignore_from_source_file,
+            &self.files_ignore_from_source_file_separator,
+        );
+    }
+}
+
+// Utility function to read the newline separated entries of an `@<path>` value, other values are returned as is
+fn read_file_reference(value: &str, separator: &str) -> String {
+    let path = match value.strip_prefix('@') {
+        Some(path) if !path.is_empty() => path,
+        _ => return value.to_string(),
+    };
+
+    match fs::read_to_string(path) {
+        Ok(contents) => contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect::<Vec<&str>>()
+            .join(separator),
+        Err(e) => {
+            println!("::error::Unable to read the list file: {} ({})", path, e);
+            error::fail(FailureReason::FileNotFound);
+        }
+    }
+}
+
+// A secret input e.g the token, which is never printed: Debug outputs `***`
+#[derive(Clone, Default)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        SecretString(value)
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "***")
+    }
+}
+
+// Where the outputs are written, `dotenv` and `stdout` are for CI systems reading dotenv files e.g GitLab CI
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputTarget {
+    #[default]
+    Github,
+    Dotenv,
+    Stdout,
+}
+
+// Naming of the outputs, `node` matches the Node.js tj-actions/changed-files action
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputCompat {
+    #[default]
+    Node,
+    Native,
 }
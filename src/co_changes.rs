@@ -0,0 +1,94 @@
+use glob::Pattern;
+use serde::Deserialize;
+
+use crate::error::{self, FailureReason};
+
+// A `require_co_changes` rule: when a changed file matches an `if` pattern, a changed file has to match a `then` pattern
+#[derive(Debug, Deserialize)]
+pub struct CoChangeRule {
+    #[serde(rename = "if")]
+    pub if_patterns: Vec<String>,
+    #[serde(rename = "then")]
+    pub then_patterns: Vec<String>,
+    #[serde(skip)]
+    if_glob_patterns: Vec<Pattern>,
+    #[serde(skip)]
+    then_glob_patterns: Vec<Pattern>,
+}
+
+impl CoChangeRule {
+    // A short description used in the messages e.g `if: src/api/** then: docs/api/**`
+    pub fn describe(&self) -> String {
+        format!("if: {} then: {}", self.if_patterns.join(", "), self.then_patterns.join(", "))
+    }
+}
+
+// Utility function to parse the `require_co_changes` input, a YAML list of `{if: [patterns], then: [patterns]}`
+pub fn parse_co_change_rules(require_co_changes: &str) -> Vec<CoChangeRule> {
+    if require_co_changes.trim().is_empty() {
+        return Vec::new();
+    }
+
+    let mut rules: Vec<CoChangeRule> = match serde_yaml::from_str(require_co_changes) {
+        Ok(rules) => rules,
+        Err(e) => {
+            error::invalid(&format!("Invalid require_co_changes: {}", e), FailureReason::InvalidInput);
+            return Vec::new();
+        }
+    };
+
+    for rule in &mut rules {
+        if rule.if_patterns.is_empty() || rule.then_patterns.is_empty() {
+            error::invalid(
+                &format!("Invalid require_co_changes rule ({}): `if` and `then` need at least one pattern", rule.describe()),
+                FailureReason::InvalidInput,
+            );
+        }
+
+        rule.if_glob_patterns = compile_patterns(&rule.if_patterns);
+        rule.then_glob_patterns = compile_patterns(&rule.then_patterns);
+    }
+
+    rules
+}
+
+fn compile_patterns(patterns: &[String]) -> Vec<Pattern> {
+    patterns
+        .iter()
+        .filter_map(|pattern| match Pattern::new(pattern) {
+            Ok(glob_pattern) => Some(glob_pattern),
+            Err(e) => {
+                error::invalid(&format!("Invalid require_co_changes pattern: {} ({})", pattern, e), FailureReason::InvalidPattern);
+                None
+            }
+        })
+        .collect()
+}
+
+// Utility function to evaluate the rules in order against the changed files, returns every violated rule
+// with the files which triggered it
+pub fn get_co_change_violations<'a>(rules: &'a [CoChangeRule], changed_files: &[String]) -> Vec<(&'a CoChangeRule, Vec<String>)> {
+    let mut violations: Vec<(&CoChangeRule, Vec<String>)> = Vec::new();
+
+    for rule in rules {
+        let triggering_files: Vec<String> = changed_files
+            .iter()
+            .filter(|path| rule.if_glob_patterns.iter().any(|pattern| pattern.matches(path)))
+            .cloned()
+            .collect();
+
+        if triggering_files.is_empty() {
+            continue;
+        }
+
+        let is_co_changed = changed_files
+            .iter()
+            .any(|path| rule.then_glob_patterns.iter().any(|pattern| pattern.matches(path)));
+
+        if !is_co_changed {
+            violations.push((rule, triggering_files));
+        }
+    }
+
+    violations
+}
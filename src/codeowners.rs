@@ -0,0 +1,269 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use glob::{MatchOptions, Pattern};
+
+use crate::error::{self, FailureReason};
+
+// Locations searched for a CODEOWNERS file, in the order GitHub uses
+const CODEOWNERS_LOCATIONS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+// A single CODEOWNERS rule: the owners assigned to a pattern
+#[derive(Debug)]
+pub struct CodeOwnersRule {
+    pub owners: Vec<String>,
+    glob_pattern: Pattern,
+    dir_only: bool,
+    match_ancestors: bool,
+}
+
+impl CodeOwnersRule {
+    // Compile a CODEOWNERS pattern following GitHub's (gitignore like) matching rules:
+    // - A leading `/` or a `/` in the middle anchors the pattern to the repository root,
+    //   otherwise the pattern matches at any depth.
+    // - A trailing `/` only matches directories (and everything beneath them).
+    // - A pattern matching a directory owns every file within it, except patterns
+    //   ending in `/*` which only match the files directly inside the directory.
+    pub fn new(pattern: &str, owners: Vec<String>) -> Option<Self> {
+        let mut glob = pattern.replace("\\#", "#");
+
+        let dir_only = glob.ends_with('/');
+        if dir_only {
+            glob = glob.trim_end_matches('/').to_string();
+        }
+
+        let anchored = glob.starts_with('/') || glob.contains('/');
+        glob = glob.trim_start_matches('/').to_string();
+
+        if glob.is_empty() {
+            // A bare `/` matches the whole repository
+            glob = "**".to_string();
+        } else if !anchored {
+            glob = format!("**/{}", glob);
+        }
+
+        let match_ancestors = !glob.ends_with("/*");
+
+        let glob_pattern = match Pattern::new(&glob) {
+            Ok(glob_pattern) => glob_pattern,
+            Err(_) => {
+                warn_or_fail!("Invalid CODEOWNERS pattern: {}", pattern);
+                return None;
+            }
+        };
+
+        Some(CodeOwnersRule {
+            owners,
+            glob_pattern,
+            dir_only,
+            match_ancestors,
+        })
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        let match_options = MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+
+        if !self.dir_only && self.glob_pattern.matches_with(path, match_options) {
+            return true;
+        }
+
+        if !self.match_ancestors {
+            return false;
+        }
+
+        // Check every parent directory of the path e.g `a` and `a/b` for `a/b/c.txt`
+        path.match_indices('/')
+            .any(|(index, _)| self.glob_pattern.matches_with(&path[..index], match_options))
+    }
+}
+
+// Utility function to locate the CODEOWNERS file, an explicit path takes precedence
+pub fn find_codeowners_file(repo_path: &Path, codeowners: &str) -> Option<PathBuf> {
+    if !codeowners.is_empty() {
+        let file_path = repo_path.join(codeowners);
+        if !file_path.is_file() {
+            println!("::error::CODEOWNERS file not found: {}", file_path.display());
+            error::fail(FailureReason::FileNotFound);
+        }
+        return Some(file_path);
+    }
+
+    CODEOWNERS_LOCATIONS
+        .iter()
+        .map(|location| repo_path.join(location))
+        .find(|file_path| file_path.is_file())
+}
+
+// Utility function to parse the contents of a CODEOWNERS file
+pub fn parse_codeowners(contents: &str) -> Vec<CodeOwnersRule> {
+    let mut rules: Vec<CodeOwnersRule> = Vec::new();
+
+    for line in contents.lines() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        // Strip trailing comments, an escaped `\#` is part of the pattern
+        let mut content = line;
+        let mut previous_char = ' ';
+        for (index, current_char) in line.char_indices() {
+            if current_char == '#' && previous_char != '\\' {
+                content = &line[..index];
+                break;
+            }
+            previous_char = current_char;
+        }
+
+        let mut parts = content.split_whitespace();
+        let pattern = match parts.next() {
+            Some(pattern) => pattern,
+            None => continue,
+        };
+
+        // Owners are `@user`, `@org/team` or email addresses
+        let owners: Vec<String> = parts
+            .filter(|owner| {
+                let is_owner = owner.starts_with('@') || owner.contains('@');
+                if !is_owner {
+                    warn_or_fail!("Invalid CODEOWNERS owner '{}' for pattern: {}", owner, pattern);
+                }
+                is_owner
+            })
+            .map(|owner| owner.to_string())
+            .collect();
+
+        if let Some(rule) = CodeOwnersRule::new(pattern, owners) {
+            rules.push(rule);
+        }
+    }
+
+    rules
+}
+
+// Utility function to read and parse a CODEOWNERS file
+pub fn read_codeowners(file_path: &Path) -> Vec<CodeOwnersRule> {
+    log!("::debug::Reading CODEOWNERS file: {}", file_path.display());
+    match fs::read_to_string(file_path) {
+        Ok(contents) => parse_codeowners(&contents),
+        Err(e) => {
+            println!("::error::Could not read CODEOWNERS file: {} ({})", file_path.display(), e);
+            error::fail(FailureReason::FileNotFound);
+        }
+    }
+}
+
+// Utility function to get the owners of a path, the last matching rule wins
+pub fn get_owners<'a>(rules: &'a [CodeOwnersRule], path: &str) -> &'a [String] {
+    match rules.iter().rev().find(|rule| rule.matches(path)) {
+        Some(rule) => &rule.owners,
+        None => &[],
+    }
+}
+
+// Utility function to group the changed files by owner, returns the
+// owner to files mapping and the files without an owner
+pub fn get_changed_files_by_owner(
+    rules: &[CodeOwnersRule],
+    changed_files: &[String],
+) -> (BTreeMap<String, Vec<String>>, Vec<String>) {
+    let mut owners_changed_files: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut unowned_changed_files: Vec<String> = Vec::new();
+
+    for path in changed_files {
+        let owners = get_owners(rules, path);
+
+        if owners.is_empty() {
+            unowned_changed_files.push(path.clone());
+            continue;
+        }
+
+        for owner in owners {
+            let files = owners_changed_files.entry(owner.clone()).or_default();
+            if !files.contains(path) {
+                files.push(path.clone());
+            }
+        }
+    }
+
+    (owners_changed_files, unowned_changed_files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODEOWNERS: &str = "\
+# Default owners
+*       @global-owner
+*.js    @js-owner # inline comment
+*.go    docs@example.com
+/build/logs/ @doctocat
+docs/*  @docs-team
+apps/   @octocat
+/scripts/ @org/scripts
+/scripts/generated
+\\#notes.md @hash-owner
+";
+
+    fn owners_of(rules: &[CodeOwnersRule], path: &str) -> Vec<String> {
+        get_owners(rules, path).to_vec()
+    }
+
+    #[test]
+    fn the_last_matching_rule_wins() {
+        let rules = parse_codeowners(CODEOWNERS);
+
+        assert_eq!(owners_of(&rules, "README.md"), ["@global-owner"]);
+        assert_eq!(owners_of(&rules, "src/app.js"), ["@js-owner"]);
+        assert_eq!(owners_of(&rules, "main.go"), ["docs@example.com"]);
+        // A later rule without owners leaves the files unowned
+        assert!(owners_of(&rules, "scripts/generated/a.sh").is_empty());
+        assert_eq!(owners_of(&rules, "scripts/build.sh"), ["@org/scripts"]);
+        assert_eq!(owners_of(&rules, "#notes.md"), ["@hash-owner"]);
+    }
+
+    #[test]
+    fn patterns_follow_the_codeowners_anchoring() {
+        let rules = parse_codeowners(CODEOWNERS);
+
+        // A leading `/` anchors the directory to the root
+        assert_eq!(owners_of(&rules, "build/logs/output.log"), ["@doctocat"]);
+        assert_eq!(owners_of(&rules, "src/build/logs/output.log"), ["@global-owner"]);
+
+        // A `/` in the middle anchors the pattern, `/*` only matches the files directly inside
+        assert_eq!(owners_of(&rules, "docs/getting-started.md"), ["@docs-team"]);
+        assert_eq!(owners_of(&rules, "docs/build-app/troubleshooting.md"), ["@global-owner"]);
+        assert_eq!(owners_of(&rules, "src/docs/getting-started.md"), ["@global-owner"]);
+
+        // A directory without a leading `/` matches at any depth, a trailing `/` only matches directories
+        assert_eq!(owners_of(&rules, "apps/web/index.md"), ["@octocat"]);
+        assert_eq!(owners_of(&rules, "src/apps/index.md"), ["@octocat"]);
+        assert_eq!(owners_of(&rules, "apps"), ["@global-owner"]);
+    }
+
+    #[test]
+    fn invalid_owners_are_skipped() {
+        let rules = parse_codeowners("*.md @docs not-an-owner\n");
+        assert_eq!(owners_of(&rules, "README.md"), ["@docs"]);
+    }
+
+    #[test]
+    fn changed_files_are_grouped_by_owner() {
+        let rules = parse_codeowners("*.rs @rust @core\n/docs/ @docs\n");
+        let changed_files: Vec<String> = ["src/main.rs", "docs/index.md", "README.md"].iter().map(|path| path.to_string()).collect();
+
+        let (owners_changed_files, unowned_changed_files) = get_changed_files_by_owner(&rules, &changed_files);
+
+        assert_eq!(owners_changed_files["@rust"], ["src/main.rs"]);
+        assert_eq!(owners_changed_files["@core"], ["src/main.rs"]);
+        assert_eq!(owners_changed_files["@docs"], ["docs/index.md"]);
+        assert_eq!(unowned_changed_files, ["README.md"]);
+    }
+}
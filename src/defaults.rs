@@ -0,0 +1,66 @@
+use glob::Pattern;
+
+use crate::utils::Diff;
+
+// Version of the default ignore patterns, bumped whenever the list changes
+pub const DEFAULT_IGNORES_VERSION: u32 = 1;
+
+// Files which rarely need a job to run: lockfiles, build output and minified assets
+pub const DEFAULT_IGNORE_PATTERNS: &[&str] = &[
+    "**/package-lock.json",
+    "**/yarn.lock",
+    "**/pnpm-lock.yaml",
+    "**/dist/**",
+    "**/*.min.js",
+    "**/*.min.css",
+];
+
+// Utility function to remove the changed files matching the default ignore patterns, from the files matching
+// the `files` patterns and the other changed files. The user patterns are evaluated after the defaults: files
+// matching a negated `files_ignore` pattern e.g `!**/dist/**` are kept.
+pub fn apply_default_ignores(diff: &mut Diff, files_ignore: &str, files_ignore_separator: &str) {
+    let default_patterns: Vec<Pattern> = DEFAULT_IGNORE_PATTERNS
+        .iter()
+        .map(|pattern| Pattern::new(pattern).unwrap())
+        .collect();
+
+    let negated_patterns: Vec<Pattern> = files_ignore
+        .split(files_ignore_separator)
+        .filter_map(|pattern| pattern.trim().strip_prefix('!'))
+        .filter_map(|pattern| Pattern::new(pattern).ok())
+        .collect();
+
+    let is_kept = |path: &str| {
+        !default_patterns.iter().any(|pattern| pattern.matches(path)) || negated_patterns.iter().any(|pattern| pattern.matches(path))
+    };
+
+    diff.files.retain(|file| is_kept(&file.path));
+    diff.other_files.retain(|file| is_kept(&file.path));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::DiffFile;
+
+    #[test]
+    fn default_ignore_patterns_compile() {
+        for pattern in DEFAULT_IGNORE_PATTERNS {
+            assert!(Pattern::new(pattern).is_ok(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn apply_default_ignores_removes_the_matching_files_and_other_files() {
+        let diff_file = |path: &str| DiffFile { path: path.to_string(), previous_path: path.to_string(), ..DiffFile::new() };
+        let mut diff = Diff::new();
+        diff.files = vec![diff_file("src/lib.rs"), diff_file("package-lock.json"), diff_file("web/dist/app.js")];
+        diff.other_files = vec![diff_file("docs/yarn.lock"), diff_file("docs/index.md"), diff_file("assets/app.min.css")];
+
+        apply_default_ignores(&mut diff, "**/*.md\n!**/dist/**", "\n");
+
+        assert_eq!(diff.paths(), vec!["src/lib.rs", "web/dist/app.js"]);
+        let other_paths: Vec<&str> = diff.other_files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(other_paths, vec!["docs/index.md"]);
+    }
+}
@@ -0,0 +1,142 @@
+use std::sync::Mutex;
+
+use crate::record;
+use crate::report;
+use crate::temp;
+use crate::utils;
+
+// Problems with the inputs found before any git command runs. With `fail_fast` the first problem exits
+// immediately, otherwise every problem is collected and reported together by `exit_on_diagnostics`.
+pub struct Diagnostics {
+    fail_fast: bool,
+    errors: Vec<(String, FailureReason)>,
+}
+
+impl Diagnostics {
+    pub const fn new() -> Self {
+        Diagnostics {
+            fail_fast: true,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, message: String, reason: FailureReason) {
+        self.errors.push((message, reason));
+    }
+}
+
+static DIAGNOSTICS: Mutex<Diagnostics> = Mutex::new(Diagnostics::new());
+
+pub fn set_fail_fast(fail_fast: &bool) {
+    DIAGNOSTICS.lock().unwrap().fail_fast = *fail_fast;
+}
+
+// Utility function to report a problem with the inputs e.g an invalid pattern or conflicting inputs
+pub fn invalid(message: &str, reason: FailureReason) {
+    let mut diagnostics = DIAGNOSTICS.lock().unwrap();
+
+    if diagnostics.fail_fast {
+        drop(diagnostics);
+        println!("::error::{}", message);
+        fail(reason);
+    }
+
+    diagnostics.push(message.to_string(), reason);
+}
+
+// Utility function to print the collected problems with the inputs and exit, the failure
+// reason is the reason of the first problem. Does nothing when there are no problems.
+pub fn exit_on_diagnostics() {
+    let errors = std::mem::take(&mut DIAGNOSTICS.lock().unwrap().errors);
+
+    let reason = match errors.first() {
+        Some((_, reason)) => *reason,
+        None => return,
+    };
+
+    println!("::group::{} problems with the inputs", errors.len());
+    for (message, _) in &errors {
+        println!("::error::{}", message);
+    }
+    println!("::endgroup::");
+
+    fail(reason);
+}
+
+// Prefix of the failure output keys, set once the inputs are parsed
+static OUTPUT_PREFIX: Mutex<String> = Mutex::new(String::new());
+
+// Reasons a run can fail, written to the `failure_reason` output before exiting
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FailureReason {
+    InvalidInput,
+    InvalidPattern,
+    GitUnavailable,
+    CommitNotFound,
+    SimilarCommitHashes,
+    NoMergeBase,
+    UnrelatedHistories,
+    FetchFailed,
+    NoChanges,
+    FileNotFound,
+    RecoveryFailed,
+    OutputWriteFailed,
+    CoChangeViolation,
+    Cancelled,
+    StrictWarning,
+}
+
+impl FailureReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureReason::InvalidInput => "invalid_input",
+            FailureReason::InvalidPattern => "invalid_pattern",
+            FailureReason::GitUnavailable => "git_unavailable",
+            FailureReason::CommitNotFound => "commit_not_found",
+            FailureReason::SimilarCommitHashes => "similar_commit_hashes",
+            FailureReason::NoMergeBase => "no_merge_base",
+            FailureReason::UnrelatedHistories => "unrelated_histories",
+            FailureReason::FetchFailed => "fetch_failed",
+            FailureReason::NoChanges => "no_changes",
+            FailureReason::FileNotFound => "file_not_found",
+            FailureReason::RecoveryFailed => "recovery_failed",
+            FailureReason::OutputWriteFailed => "output_write_failed",
+            FailureReason::CoChangeViolation => "co_change_violation",
+            FailureReason::Cancelled => "cancelled",
+            FailureReason::StrictWarning => "strict_warning",
+        }
+    }
+
+    // Problems with the inputs exit with `2` like argument errors, a cancelled run with `130`
+    // like an interrupted shell command, everything else with `1`
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            FailureReason::InvalidInput | FailureReason::InvalidPattern => 2,
+            FailureReason::Cancelled => 130,
+            _ => 1,
+        }
+    }
+}
+
+pub fn set_output_prefix(output_prefix: &str) {
+    *OUTPUT_PREFIX.lock().unwrap() = output_prefix.to_string();
+}
+
+// Utility function to write the `failed` and `failure_reason` outputs and exit with the mapped exit code
+pub fn fail(reason: FailureReason) -> ! {
+    // The outputs can't be written when writing them is what failed
+    if reason != FailureReason::OutputWriteFailed {
+        let github_output = std::env::var("GITHUB_OUTPUT").unwrap_or_default();
+        let output_prefix = OUTPUT_PREFIX.lock().unwrap().clone();
+
+        utils::set_output(&github_output, &output_prefix, "failed", "true");
+        utils::set_output(&github_output, &output_prefix, "failure_reason", reason.as_str());
+        report::write(&github_output, &output_prefix);
+    }
+
+    record::record_decision("failure_reason", reason.as_str());
+    record::save();
+    temp::cleanup(true);
+
+    std::process::exit(reason.exit_code());
+}
@@ -0,0 +1,142 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use git2::Repository;
+use glob::Pattern;
+
+use crate::args::Args;
+use crate::error::{self, FailureReason};
+use crate::utils::{self, Diff, DiffType};
+
+// A named filter set from a pattern file of the `filters_from_files` directory
+#[derive(Debug)]
+pub struct Filter {
+    pub name: String,
+    pub glob_patterns: Vec<Pattern>,
+}
+
+// Utility function to turn a file stem into a filter name usable as an output prefix and a
+// directory name: anything other than letters, digits and underscores becomes `_` e.g `api-v2` -> `api_v2`
+pub fn sanitize_filter_name(stem: &str) -> String {
+    let mut name: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+
+    name
+}
+
+// Utility function to read every pattern file of the `filters_from_files` directory, one filter per
+// file named after the file stem. The patterns use the same format as `files_from_source_file`.
+pub fn read_filters(filters_from_files: &str, repo: &Repository, max_patterns: &usize) -> Vec<Filter> {
+    if filters_from_files.is_empty() {
+        return Vec::new();
+    }
+
+    let filters_dir = utils::get_workdir(repo).join(filters_from_files);
+    let mut file_paths: Vec<_> = match fs::read_dir(&filters_dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|file_path| file_path.is_file())
+            .collect(),
+        Err(e) => {
+            println!("::error::Unable to read filters_from_files: {} ({})", filters_dir.display(), e);
+            error::fail(FailureReason::FileNotFound);
+        }
+    };
+    file_paths.sort();
+
+    let mut filters: Vec<Filter> = Vec::new();
+    let mut names: HashSet<String> = HashSet::new();
+
+    for file_path in file_paths {
+        let stem = file_path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+
+        // Hidden files e.g `.gitkeep`
+        if stem.is_empty() || stem.starts_with('.') {
+            continue;
+        }
+
+        let name = sanitize_filter_name(&stem);
+        if !names.insert(name.clone()) {
+            println!(
+                "::error::Duplicate filter name '{}' for {}, file stems must be unique after replacing special characters with `_`",
+                name,
+                file_path.display()
+            );
+            error::fail(FailureReason::InvalidInput);
+        }
+
+        let contents = match fs::read_to_string(&file_path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                println!("::error::Could not read filter file: {} ({})", file_path.display(), e);
+                error::fail(FailureReason::FileNotFound);
+            }
+        };
+
+        let glob_patterns = utils::get_glob_patterns(&contents, "\n", "", "", "", "", "", "", "", max_patterns);
+        log!("::debug::Filter {}: {} patterns", name, glob_patterns.len());
+
+        filters.push(Filter { name, glob_patterns });
+    }
+
+    filters
+}
+
+// Utility function to evaluate every filter against the shared list of changed files and write its
+// outputs prefixed with `<name>_`, with `write_output_files` also to `<output_dir>/<name>/<output_prefix><output>.txt`
+pub fn write_filters_outputs(filters: &[Filter], changed_files: &Diff, repo: &Repository, github_output: &str, args: &Args) {
+    for filter in filters {
+        let filter_changed_files = changed_files.matching(&filter.glob_patterns, &utils::DiffSettings::from_args(args));
+        let all_changed_files = filter_changed_files.paths_of_types(&utils::ALL_CHANGED_DIFF_TYPES);
+        let all_modified_files = filter_changed_files.paths_of_types(&utils::ALL_MODIFIED_DIFF_TYPES);
+
+        let outputs = [
+            ("any_changed", (!all_changed_files.is_empty()).to_string()),
+            ("all_changed_files", utils::format_files_output(&all_changed_files, args)),
+            ("all_modified_files", utils::format_files_output(&all_modified_files, args)),
+            ("added_files", utils::format_files_output(&filter_changed_files.paths_of_types(&[DiffType::Added]), args)),
+            ("deleted_files", utils::format_files_output(&filter_changed_files.paths_of_types(&[DiffType::Deleted]), args)),
+            ("modified_files", utils::format_files_output(&filter_changed_files.paths_of_types(&[DiffType::Modified]), args)),
+            ("renamed_files", utils::format_files_output(&filter_changed_files.paths_of_types(&[DiffType::Renamed]), args)),
+        ];
+
+        let output_prefix = format!("{}{}_", args.output_prefix, filter.name);
+        for (key, value) in &outputs {
+            utils::set_output(github_output, &output_prefix, key, value);
+        }
+
+        if args.write_output_files {
+            write_output_files(
+                &utils::get_workdir(repo).join(&args.output_dir).join(&filter.name),
+                &args.output_prefix,
+                &outputs,
+                &args.json,
+            );
+        }
+    }
+}
+
+// Utility function to write the outputs of a filter to one file per output
+fn write_output_files(output_dir: &Path, output_prefix: &str, outputs: &[(&str, String)], json: &bool) {
+    if let Err(e) = fs::create_dir_all(output_dir) {
+        println!("::error::Unable to create the output directory: {} ({})", output_dir.display(), e);
+        error::fail(FailureReason::OutputWriteFailed);
+    }
+
+    let extension = if *json { "json" } else { "txt" };
+
+    for (key, value) in outputs {
+        let output_file = output_dir.join(format!("{}{}.{}", output_prefix, key, extension));
+        if let Err(e) = fs::write(&output_file, value) {
+            println!("::error::Unable to write the output file: {} ({})", output_file.display(), e);
+            error::fail(FailureReason::OutputWriteFailed);
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::path::Path;
+
+use git2::{Commit, ObjectType, Repository, TreeWalkMode, TreeWalkResult};
+use glob::{MatchOptions, Pattern};
+
+// A `.gitattributes` line assigning the attribute, relative to the directory of its file
+#[derive(Debug)]
+pub struct AttributeRule {
+    // The directory of the `.gitattributes` file e.g `docs/`, empty for the root
+    pub dir: String,
+    // `Some(true)` when set, `Some(false)` when unset (`-attr` or `attr=false`) and `None` when unspecified (`!attr`)
+    pub state: Option<bool>,
+    glob_pattern: Pattern,
+}
+
+impl AttributeRule {
+    // Patterns without a `/` match the file name at any depth below the directory, otherwise the pattern
+    // is anchored to the directory
+    pub fn new(pattern: &str, dir: &str, state: Option<bool>) -> Option<Self> {
+        let glob = if pattern.trim_end_matches('/').contains('/') {
+            pattern.trim_start_matches('/').to_string()
+        } else {
+            format!("**/{}", pattern)
+        };
+
+        let glob_pattern = match Pattern::new(&glob) {
+            Ok(glob_pattern) => glob_pattern,
+            Err(_) => {
+                warn_or_fail!("Invalid .gitattributes pattern in {}.gitattributes: {}", dir, pattern);
+                return None;
+            }
+        };
+
+        Some(AttributeRule {
+            dir: dir.to_string(),
+            state,
+            glob_pattern,
+        })
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        let match_options = MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+
+        match path.strip_prefix(&self.dir) {
+            Some(relative_path) => self.glob_pattern.matches_with(relative_path, match_options),
+            None => false,
+        }
+    }
+}
+
+// Utility function to parse the rules of a `.gitattributes` file for a single attribute
+pub fn parse_gitattributes(contents: &str, dir: &str, attribute_name: &str) -> Vec<AttributeRule> {
+    let mut rules: Vec<AttributeRule> = Vec::new();
+
+    for line in contents.lines() {
+        let line = line.trim();
+
+        // Comments and macro definitions
+        if line.is_empty() || line.starts_with('#') || line.starts_with("[attr]") {
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let pattern = match parts.next() {
+            Some(pattern) => pattern,
+            None => continue,
+        };
+
+        // The last assignment of the attribute on the line wins
+        let mut state: Option<Option<bool>> = None;
+        for attribute in parts {
+            if attribute == attribute_name {
+                state = Some(Some(true));
+            } else if attribute.strip_prefix('-') == Some(attribute_name) {
+                state = Some(Some(false));
+            } else if attribute.strip_prefix('!') == Some(attribute_name) {
+                state = Some(None);
+            } else if let Some((name, value)) = attribute.split_once('=') {
+                if name == attribute_name {
+                    state = Some(Some(value != "false"));
+                }
+            }
+        }
+
+        if let Some(state) = state {
+            if let Some(rule) = AttributeRule::new(pattern, dir, state) {
+                rules.push(rule);
+            }
+        }
+    }
+
+    rules
+}
+
+// Utility function to read the rules for an attribute from every `.gitattributes` file committed in a commit,
+// which works without a working tree. Rules of nested files come after the rules of their parent directories.
+pub fn read_gitattributes(repo: &Repository, commit: &Commit, attribute_name: &str) -> Vec<AttributeRule> {
+    let tree = commit.tree().unwrap();
+    let mut gitattributes_files: Vec<(String, git2::Oid)> = Vec::new();
+
+    tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+        if entry.name() == Some(".gitattributes") && entry.kind() == Some(ObjectType::Blob) {
+            gitattributes_files.push((root.to_string(), entry.id()));
+        }
+        TreeWalkResult::Ok
+    })
+    .unwrap();
+
+    // Shallower files first so the rules of nested files take precedence
+    gitattributes_files.sort_by_key(|(dir, _)| dir.matches('/').count());
+
+    let mut rules: Vec<AttributeRule> = Vec::new();
+    for (dir, oid) in gitattributes_files {
+        log!("::debug::Reading {}.gitattributes", dir);
+        match repo.find_blob(oid) {
+            Ok(blob) => rules.extend(parse_gitattributes(&String::from_utf8_lossy(blob.content()), &dir, attribute_name)),
+            Err(e) => warn_or_fail!("Unable to read {}: {}", Path::new(&dir).join(".gitattributes").display(), e),
+        }
+    }
+
+    rules
+}
+
+// Utility function to check whether the attribute is set for a path, the last matching rule wins
+pub fn is_attribute_set(rules: &[AttributeRule], path: &str) -> bool {
+    match rules.iter().rev().find(|rule| rule.matches(path)) {
+        Some(rule) => rule.state.unwrap_or(false),
+        None => false,
+    }
+}
@@ -0,0 +1,663 @@
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeSet;
+use std::hash::{BuildHasher, Hasher};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::args::{OutputCompat, OutputTarget};
+use crate::error::{self, FailureReason};
+
+// File list outputs as `(node, native)` names, each also has a `<name>_count` output.
+// The node names are the output names of the Node.js action and the keys used internally. The native names
+// say which files a list holds e.g `changed_and_deleted_files` instead of `all_modified_files`.
+pub const FILE_LIST_OUTPUT_KEYS: [(&str, &str); 17] = [
+    ("added_files", "added_files"),
+    ("copied_files", "copied_files"),
+    ("deleted_files", "deleted_files"),
+    ("modified_files", "modified_files"),
+    ("renamed_files", "renamed_files"),
+    ("all_old_new_renamed_files", "renamed_file_pairs"),
+    ("moved_files", "moved_files"),
+    ("renamed_and_modified_files", "renamed_and_modified_files"),
+    ("type_changed_files", "type_changed_files"),
+    ("unmerged_files", "unmerged_files"),
+    ("unknown_files", "unknown_files"),
+    ("all_changed_and_modified_files", "any_status_files"),
+    ("all_changed_files", "changed_files"),
+    ("all_modified_files", "changed_and_deleted_files"),
+    ("other_changed_files", "unmatched_files"),
+    ("generated_changed_files", "generated_files"),
+    ("small_changed_files", "small_files"),
+];
+
+// Every other output which can be requested with the `outputs` input as `(node, native)` names. The native
+// names of boolean outputs start with `has_` or `is_`.
+pub const OUTPUT_KEYS: [(&str, &str); 64] = [
+    ("any_changed", "has_changes"),
+    ("any_other_changed", "has_unmatched_changes"),
+    ("any_modified", "has_changes_or_deletions"),
+    ("any_deleted", "has_deletions"),
+    ("remote_url", "remote_url"),
+    ("repository_root", "repository_root"),
+    ("head_ref_resolved", "head_ref_resolved"),
+    ("base_sha_timestamp", "base_sha_timestamp"),
+    ("base_sha_author_name", "base_sha_author_name"),
+    ("base_sha_author_email", "base_sha_author_email"),
+    ("sha_timestamp", "sha_timestamp"),
+    ("sha_author_name", "sha_author_name"),
+    ("sha_author_email", "sha_author_email"),
+    ("case_only_renamed_files", "case_only_renamed_files"),
+    ("renamed_files_similarity", "rename_similarity"),
+    ("rename_chains", "rename_chains"),
+    ("changed_extensions", "changed_extensions"),
+    ("changed_files_by_extension", "files_by_extension"),
+    ("changed_files_urls", "file_urls"),
+    ("changed_workflow_files", "workflow_files"),
+    ("workflow_files_changed", "has_workflow_changes"),
+    ("security_sensitive_files_changed", "has_security_sensitive_changes"),
+    ("deleted_dirs", "deleted_dirs"),
+    ("dir_stats", "dir_stats"),
+    ("effective_config", "effective_config"),
+    ("filter_only_changed_files", "normalization_only_files"),
+    ("default_ignores_applied", "default_ignores_applied"),
+    ("deep_changed_files_count", "deep_files_count"),
+    ("large_changed_files", "large_files"),
+    ("owners", "owners"),
+    ("owners_changed_files", "files_by_owner"),
+    ("unowned_changed_files", "unowned_files"),
+    ("co_change_violations", "co_change_violations"),
+    ("per_commit_changed_files", "files_by_commit"),
+    ("shard_index", "shard_index"),
+    ("shard_count", "shard_count"),
+    ("file_churn", "file_churn"),
+    ("high_churn_files", "high_churn_files"),
+    ("commits_count", "commits_count"),
+    ("behind_count", "behind_count"),
+    ("commits_count_exceeded", "is_commits_count_exceeded"),
+    ("unrelated_histories", "has_unrelated_histories"),
+    ("deltas_limit_exceeded", "is_deltas_limit_exceeded"),
+    ("stale_event_data", "is_event_data_stale"),
+    ("refs_updated", "refs_updated"),
+    ("checkout_mismatch", "is_checkout_mismatch"),
+    ("default_branch", "default_branch"),
+    ("base_strategy", "base_strategy"),
+    ("base_tag", "base_tag"),
+    ("head_tag", "head_tag"),
+    ("base_tree_file", "base_tree_file"),
+    ("head_tree_file", "head_tree_file"),
+    ("submodule_config_changed", "has_submodule_config_changes"),
+    ("submodule_config_changes", "submodule_config_changes"),
+    ("pattern_match_counts", "pattern_match_counts"),
+    ("match_provenance", "match_provenance"),
+    ("fetch_duration_ms", "fetch_duration_ms"),
+    ("sha_resolution_duration_ms", "sha_resolution_duration_ms"),
+    ("diff_duration_ms", "diff_duration_ms"),
+    ("output_write_duration_ms", "output_write_duration_ms"),
+    ("total_duration_ms", "total_duration_ms"),
+    ("result", "result"),
+    ("failed", "has_failed"),
+    ("failure_reason", "failure_reason"),
+];
+
+// Outputs of every filter set and range, written as `<name>_<output>` e.g `docs_any_changed`
+pub const GROUP_OUTPUT_KEYS: [&str; 7] = [
+    "any_changed",
+    "all_changed_files",
+    "all_modified_files",
+    "added_files",
+    "deleted_files",
+    "modified_files",
+    "renamed_files",
+];
+
+// Outputs written whether or not they're requested
+const ALWAYS_WRITTEN_OUTPUT_KEYS: [&str; 3] = ["failed", "failure_reason", "result"];
+
+// The outputs requested with the `outputs` input, `None` writes every output
+static REQUESTED_OUTPUTS: Mutex<Option<BTreeSet<String>>> = Mutex::new(None);
+
+// The `output_prefix` input, the outputs of a filter set or a range are prefixed with `<output_prefix><name>_`
+static OUTPUT_PREFIX: Mutex<String> = Mutex::new(String::new());
+
+// The naming used for the outputs
+static OUTPUT_COMPAT: Mutex<OutputCompat> = Mutex::new(OutputCompat::Node);
+
+pub fn set_output_compat(output_compat: &OutputCompat) {
+    *OUTPUT_COMPAT.lock().unwrap() = *output_compat;
+}
+
+// Where the outputs are written and the dotenv file of the `dotenv` target
+static OUTPUT_TARGET: Mutex<(OutputTarget, String)> = Mutex::new((OutputTarget::Github, String::new()));
+
+pub fn set_output_target(output_target: &OutputTarget, output_dotenv_file: &str) {
+    if *output_target == OutputTarget::Dotenv && output_dotenv_file.is_empty() {
+        error::invalid("`output_target: dotenv` requires `output_dotenv_file` to be set", FailureReason::InvalidInput);
+    }
+
+    *OUTPUT_TARGET.lock().unwrap() = (*output_target, output_dotenv_file.to_string());
+}
+
+pub fn get_output_target() -> (OutputTarget, String) {
+    OUTPUT_TARGET.lock().unwrap().clone()
+}
+
+// The largest value written to GITHUB_OUTPUT and the directory larger values are written to, unset until
+// the repository is opened
+static OUTPUT_SIZE_LIMIT: Mutex<(usize, Option<PathBuf>)> = Mutex::new((0, None));
+
+pub fn set_output_size_limit(max_github_output_bytes: &usize, output_dir: PathBuf) {
+    *OUTPUT_SIZE_LIMIT.lock().unwrap() = (*max_github_output_bytes, Some(output_dir));
+}
+
+pub fn get_output_size_limit() -> (usize, Option<PathBuf>) {
+    OUTPUT_SIZE_LIMIT.lock().unwrap().clone()
+}
+
+// Utility function to pick the name of a `(node, native)` pair for the output naming
+fn select_name<'a>(names: &(&'a str, &'a str), output_compat: &OutputCompat) -> &'a str {
+    match output_compat {
+        OutputCompat::Node => names.0,
+        OutputCompat::Native => names.1,
+    }
+}
+
+// Utility function to get the name of every output for the output naming
+pub fn get_output_keys() -> Vec<String> {
+    let output_compat = *OUTPUT_COMPAT.lock().unwrap();
+    let mut output_keys: Vec<String> = Vec::new();
+
+    for names in &FILE_LIST_OUTPUT_KEYS {
+        let name = select_name(names, &output_compat);
+        output_keys.push(name.to_string());
+        output_keys.push(format!("{}_count", name));
+    }
+    output_keys.extend(OUTPUT_KEYS.iter().map(|names| select_name(names, &output_compat).to_string()));
+
+    output_keys
+}
+
+// Utility function to get the output name of an internal (node) key e.g `all_old_new_renamed_files_count`
+// is `renamed_file_pairs_count` with the native naming. Keys without an entry are returned as is.
+pub fn get_output_name(key: &str) -> String {
+    let output_compat = *OUTPUT_COMPAT.lock().unwrap();
+
+    if let Some(names) = OUTPUT_KEYS.iter().chain(FILE_LIST_OUTPUT_KEYS.iter()).find(|names| names.0 == key) {
+        return select_name(names, &output_compat).to_string();
+    }
+
+    if let Some(list_key) = key.strip_suffix("_count") {
+        if let Some(names) = FILE_LIST_OUTPUT_KEYS.iter().find(|names| names.0 == list_key) {
+            return format!("{}_count", select_name(names, &output_compat));
+        }
+    }
+
+    key.to_string()
+}
+
+// Utility function to format a boolean-ish string input inside a JSON structure e.g `effective_config`. The
+// native naming uses JSON booleans, the node naming keeps the `true` and `false` strings of the Node.js action.
+pub fn format_json_boolean(value: &str, output_compat: &OutputCompat) -> serde_json::Value {
+    match (output_compat, value) {
+        (OutputCompat::Native, "true") => serde_json::Value::Bool(true),
+        (OutputCompat::Native, "false") => serde_json::Value::Bool(false),
+        _ => serde_json::Value::String(value.to_string()),
+    }
+}
+
+// Utility function to get the output names of the outputs of every filter set and range for the output naming
+fn get_group_output_names() -> Vec<String> {
+    GROUP_OUTPUT_KEYS.iter().map(|key| get_output_name(key)).collect()
+}
+
+// Utility function to check whether a requested output is the output of a filter set or a range e.g `docs_any_changed`
+fn is_group_output_key(key: &str) -> bool {
+    get_group_output_names().iter().any(|group_key| {
+        key.strip_suffix(group_key.as_str())
+            .and_then(|name| name.strip_suffix('_'))
+            .map_or(false, |name| !name.is_empty())
+    })
+}
+
+// Utility function to parse the `outputs` input, a comma or newline separated list of output keys or `all`
+pub fn set_requested_outputs(outputs: &str, output_prefix: &str) {
+    *OUTPUT_PREFIX.lock().unwrap() = output_prefix.to_string();
+
+    let requested_outputs: BTreeSet<String> = outputs
+        .split([',', '\n'])
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+        .collect();
+
+    if requested_outputs.is_empty() || requested_outputs.contains("all") {
+        *REQUESTED_OUTPUTS.lock().unwrap() = None;
+        return;
+    }
+
+    let output_keys = get_output_keys();
+    let unknown_outputs: Vec<&String> = requested_outputs
+        .iter()
+        .filter(|key| !output_keys.contains(key) && !is_group_output_key(key))
+        .collect();
+
+    if !unknown_outputs.is_empty() {
+        error::invalid(
+            &format!(
+                "Unknown outputs: {}. Valid outputs are: all, {} and `<filter or range name>_<output>` for: {}",
+                unknown_outputs.iter().map(|key| key.as_str()).collect::<Vec<&str>>().join(", "),
+                output_keys.join(", "),
+                get_group_output_names().join(", ")
+            ),
+            FailureReason::InvalidInput,
+        );
+    }
+
+    *REQUESTED_OUTPUTS.lock().unwrap() = Some(requested_outputs);
+}
+
+// Utility function to check whether an output was listed in the `outputs` input, not just included by `all`
+pub fn is_explicitly_requested(key: &str) -> bool {
+    match REQUESTED_OUTPUTS.lock().unwrap().as_ref() {
+        None => false,
+        Some(requested_outputs) => requested_outputs.contains(&get_output_name(key)),
+    }
+}
+
+// Utility function to check whether an output (internal key without the output prefix) should be computed and written
+pub fn is_requested(key: &str) -> bool {
+    match REQUESTED_OUTPUTS.lock().unwrap().as_ref() {
+        None => true,
+        Some(requested_outputs) => {
+            requested_outputs.contains(&get_output_name(key)) || ALWAYS_WRITTEN_OUTPUT_KEYS.contains(&key)
+        }
+    }
+}
+
+// Utility function to check whether an output written with `output_prefix` should be computed and written. The
+// outputs of a filter set or a range are requested by their name e.g `docs_any_changed`.
+pub fn is_requested_with_prefix(output_prefix: &str, key: &str) -> bool {
+    let group = output_prefix
+        .strip_prefix(OUTPUT_PREFIX.lock().unwrap().as_str())
+        .filter(|group| !group.is_empty())
+        .map(|group| group.to_string());
+
+    match group {
+        None => is_requested(key),
+        Some(group) => match REQUESTED_OUTPUTS.lock().unwrap().as_ref() {
+            None => true,
+            Some(requested_outputs) => requested_outputs.contains(&format!("{}{}", group, get_output_name(key))),
+        },
+    }
+}
+
+// Utility function to validate an output key against `[A-Za-z_][A-Za-z0-9_-]*`
+pub fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        None => false,
+        Some(first_char) => {
+            (first_char.is_ascii_alphabetic() || first_char == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        }
+    }
+}
+
+// Utility function to choose a heredoc delimiter which doesn't occur anywhere in the value
+pub fn get_delimiter(value: &str) -> String {
+    loop {
+        // RandomState is seeded randomly for every instance
+        let random = RandomState::new().build_hasher().finish();
+        let delimiter = format!("ghadelimiter_{:016x}", random);
+
+        if !value.contains(&delimiter) {
+            return delimiter;
+        }
+    }
+}
+
+// Utility function to format an output in the GITHUB_OUTPUT file format. Values containing a
+// newline use a heredoc with a random delimiter so the value can't end the heredoc early.
+pub fn format_output(key: &str, value: &str) -> String {
+    if !is_valid_key(key) {
+        println!(
+            "::error::Invalid output key: '{}'. Only letters, digits, `_` and `-` are allowed and it must not start with a digit or `-`",
+            key
+        );
+        error::fail(FailureReason::InvalidInput);
+    }
+
+    if value.contains('\n') || value.contains('\r') {
+        let delimiter = get_delimiter(value);
+        format!("{}<<{}\n{}\n{}\n", key, delimiter, value, delimiter)
+    } else {
+        format!("{}={}\n", key, value)
+    }
+}
+
+// Utility function to format an output as a dotenv line e.g `ANY_CHANGED="true"`. The key is upper-cased with
+// `-` replaced by `_`, the value is double quoted with `\`, `"`, `$` and backticks escaped and newlines as `\n`.
+pub fn format_dotenv_output(key: &str, value: &str) -> String {
+    let mut escaped_value = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '\\' => escaped_value.push_str("\\\\"),
+            '"' => escaped_value.push_str("\\\""),
+            '$' => escaped_value.push_str("\\$"),
+            '`' => escaped_value.push_str("\\`"),
+            '\n' => escaped_value.push_str("\\n"),
+            '\r' => escaped_value.push_str("\\r"),
+            _ => escaped_value.push(c),
+        }
+    }
+
+    format!("{}=\"{}\"\n", key.to_uppercase().replace('-', "_"), escaped_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo;
+
+    #[test]
+    fn requested_outputs_accept_the_outputs_of_filters_and_ranges() {
+        let _lock = test_repo::lock_global_settings();
+        set_requested_outputs("all_changed_files,docs_any_changed", "pre_");
+
+        assert!(is_requested_with_prefix("pre_", "all_changed_files"));
+        assert!(!is_requested_with_prefix("pre_", "any_changed"));
+        assert!(is_requested_with_prefix("pre_docs_", "any_changed"));
+        assert!(!is_requested_with_prefix("pre_docs_", "all_changed_files"));
+        assert!(!is_requested_with_prefix("pre_src_", "any_changed"));
+        assert!(is_requested_with_prefix("pre_", "failed"));
+
+        set_requested_outputs("all", "");
+        assert!(is_requested_with_prefix("docs_", "any_changed"));
+    }
+
+    #[test]
+    fn group_output_keys_need_a_name() {
+        let _lock = test_repo::lock_global_settings();
+        assert!(is_group_output_key("docs_any_changed"));
+        assert!(is_group_output_key("api_v2_renamed_files"));
+        assert!(!is_group_output_key("any_changed"));
+        assert!(!is_group_output_key("_any_changed"));
+        assert!(!is_group_output_key("docs_anything"));
+    }
+
+    // Outputs of the Node.js action written by this crate, the node naming must keep them as is
+    const NODE_ACTION_OUTPUT_KEYS: [&str; 15] = [
+        "added_files",
+        "copied_files",
+        "deleted_files",
+        "modified_files",
+        "renamed_files",
+        "all_old_new_renamed_files",
+        "type_changed_files",
+        "unmerged_files",
+        "unknown_files",
+        "all_changed_and_modified_files",
+        "all_changed_files",
+        "all_modified_files",
+        "other_changed_files",
+        "any_changed",
+        "any_modified",
+    ];
+
+    // Outputs deliberately named the same with both namings, every other output needs its own native name
+    const SHARED_OUTPUT_KEYS: [&str; 51] = [
+        "added_files",
+        "copied_files",
+        "deleted_files",
+        "modified_files",
+        "renamed_files",
+        "moved_files",
+        "renamed_and_modified_files",
+        "type_changed_files",
+        "unmerged_files",
+        "unknown_files",
+        "remote_url",
+        "repository_root",
+        "head_ref_resolved",
+        "base_sha_timestamp",
+        "base_sha_author_name",
+        "base_sha_author_email",
+        "sha_timestamp",
+        "sha_author_name",
+        "sha_author_email",
+        "case_only_renamed_files",
+        "rename_chains",
+        "changed_extensions",
+        "deleted_dirs",
+        "dir_stats",
+        "effective_config",
+        "default_ignores_applied",
+        "owners",
+        "co_change_violations",
+        "shard_index",
+        "shard_count",
+        "file_churn",
+        "high_churn_files",
+        "commits_count",
+        "behind_count",
+        "refs_updated",
+        "default_branch",
+        "base_strategy",
+        "base_tag",
+        "head_tag",
+        "base_tree_file",
+        "head_tree_file",
+        "submodule_config_changes",
+        "pattern_match_counts",
+        "match_provenance",
+        "fetch_duration_ms",
+        "sha_resolution_duration_ms",
+        "diff_duration_ms",
+        "output_write_duration_ms",
+        "total_duration_ms",
+        "result",
+        "failure_reason",
+    ];
+
+    fn get_output_keys_with(output_compat: OutputCompat) -> Vec<String> {
+        set_output_compat(&output_compat);
+        let output_keys = get_output_keys();
+        set_output_compat(&OutputCompat::Node);
+        output_keys
+    }
+
+    #[test]
+    fn every_output_has_a_name_for_both_namings() {
+        let all_names: Vec<&(&str, &str)> = FILE_LIST_OUTPUT_KEYS.iter().chain(OUTPUT_KEYS.iter()).collect();
+
+        for (node_name, native_name) in &all_names {
+            assert!(is_valid_key(node_name) && is_valid_key(native_name), "{}", node_name);
+            assert_eq!(
+                node_name == native_name,
+                SHARED_OUTPUT_KEYS.contains(node_name),
+                "{} is named {} with the native naming, list it in SHARED_OUTPUT_KEYS only when the names are the same",
+                node_name,
+                native_name
+            );
+        }
+
+        for key in SHARED_OUTPUT_KEYS {
+            assert!(all_names.iter().any(|names| names.0 == key), "{}", key);
+        }
+    }
+
+    #[test]
+    fn output_names_are_unique_with_both_namings() {
+        let _lock = test_repo::lock_global_settings();
+
+        for output_compat in [OutputCompat::Node, OutputCompat::Native] {
+            let output_keys = get_output_keys_with(output_compat);
+            let unique_keys: BTreeSet<&String> = output_keys.iter().collect();
+            assert_eq!(unique_keys.len(), output_keys.len(), "{:?}", output_compat);
+            assert_eq!(output_keys.len(), FILE_LIST_OUTPUT_KEYS.len() * 2 + OUTPUT_KEYS.len());
+        }
+    }
+
+    #[test]
+    fn node_naming_keeps_the_node_action_outputs() {
+        let _lock = test_repo::lock_global_settings();
+        set_output_compat(&OutputCompat::Node);
+
+        for (node_name, native_name) in FILE_LIST_OUTPUT_KEYS.iter().chain(OUTPUT_KEYS.iter()) {
+            assert_eq!(get_output_name(node_name), *node_name);
+            assert_eq!(get_output_name(native_name), *native_name);
+        }
+        for key in NODE_ACTION_OUTPUT_KEYS {
+            assert_eq!(get_output_name(key), key);
+        }
+        assert_eq!(get_output_name("all_old_new_renamed_files_count"), "all_old_new_renamed_files_count");
+        assert!(get_output_keys().contains(&"any_changed".to_string()));
+    }
+
+    #[test]
+    fn native_naming_renames_the_outputs() {
+        let _lock = test_repo::lock_global_settings();
+        set_output_compat(&OutputCompat::Native);
+
+        for (node_name, native_name) in FILE_LIST_OUTPUT_KEYS.iter().chain(OUTPUT_KEYS.iter()) {
+            assert_eq!(get_output_name(node_name), *native_name);
+        }
+        for (node_name, native_name) in FILE_LIST_OUTPUT_KEYS {
+            assert_eq!(get_output_name(&format!("{}_count", node_name)), format!("{}_count", native_name));
+        }
+        assert_eq!(get_output_name("any_changed"), "has_changes");
+        assert_eq!(get_output_name("all_old_new_renamed_files"), "renamed_file_pairs");
+        assert_eq!(get_output_name("failed"), "has_failed");
+        assert_eq!(get_output_name("docs_output_too_large"), "docs_output_too_large");
+
+        set_requested_outputs("changed_files,docs_has_changes", "");
+        assert!(is_requested("all_changed_files"));
+        assert!(!is_requested("any_changed"));
+        assert!(is_requested("failed"));
+        assert!(is_requested_with_prefix("docs_", "any_changed"));
+        assert!(is_group_output_key("docs_changed_and_deleted_files"));
+        assert!(!is_group_output_key("docs_any_changed"));
+
+        set_requested_outputs("all", "");
+        set_output_compat(&OutputCompat::Node);
+    }
+
+    #[test]
+    fn json_booleans_follow_the_output_naming() {
+        assert_eq!(format_json_boolean("true", &OutputCompat::Node), serde_json::json!("true"));
+        assert_eq!(format_json_boolean("false", &OutputCompat::Node), serde_json::json!("false"));
+        assert_eq!(format_json_boolean("true", &OutputCompat::Native), serde_json::json!(true));
+        assert_eq!(format_json_boolean("false", &OutputCompat::Native), serde_json::json!(false));
+        assert_eq!(format_json_boolean("", &OutputCompat::Native), serde_json::json!(""));
+
+        let args = test_repo::parse_args(&["--forced", "true", "--output-compat", "native"]);
+        assert_eq!(args.effective_config()["forced"], serde_json::json!(true));
+        let args = test_repo::parse_args(&["--forced", "true"]);
+        assert_eq!(args.effective_config()["forced"], serde_json::json!("true"));
+    }
+
+    // Parse a GITHUB_OUTPUT file like the runner, a heredoc value is every line up to its delimiter
+    fn parse_github_output(contents: &str) -> Vec<(String, String)> {
+        let mut outputs: Vec<(String, String)> = Vec::new();
+        let mut lines = contents.split('\n');
+
+        while let Some(line) = lines.next() {
+            if line.is_empty() {
+                continue;
+            }
+            if let Some((key, delimiter)) = line.split_once("<<") {
+                let value_lines: Vec<&str> = lines.by_ref().take_while(|value_line| *value_line != delimiter).collect();
+                outputs.push((key.to_string(), value_lines.join("\n")));
+            } else {
+                let (key, value) = line.split_once('=').unwrap();
+                outputs.push((key.to_string(), value.to_string()));
+            }
+        }
+
+        outputs
+    }
+
+    #[test]
+    fn heredoc_value_containing_eof_round_trips() {
+        let value = "src/a.txt\nEOF\nsrc/b.txt";
+        let output = format_output("all_changed_files", value);
+
+        let delimiter = output.lines().next().unwrap().split_once("<<").unwrap().1;
+        assert!(!value.contains(delimiter));
+        assert_eq!(output, format!("all_changed_files<<{}\n{}\n{}\n", delimiter, value, delimiter));
+        assert_eq!(
+            parse_github_output(&format!("{}{}", output, format_output("any_changed", "true"))),
+            vec![("all_changed_files".to_string(), value.to_string()), ("any_changed".to_string(), "true".to_string())]
+        );
+    }
+
+    #[test]
+    fn heredoc_value_with_a_trailing_newline_round_trips() {
+        let value = "src/a.txt\nsrc/b.txt\n";
+        let output = format_output("all_changed_files", value);
+
+        let delimiter = output.lines().next().unwrap().split_once("<<").unwrap().1;
+        assert!(!value.contains(delimiter));
+        assert_eq!(output, format!("all_changed_files<<{}\n{}\n{}\n", delimiter, value, delimiter));
+        assert_eq!(parse_github_output(&output), vec![("all_changed_files".to_string(), value.to_string())]);
+    }
+
+    // Parse a dotenv file written by `format_dotenv_output`, undoing the escaping of the quoted values
+    fn parse_dotenv_output(contents: &str) -> Vec<(String, String)> {
+        contents
+            .lines()
+            .map(|line| {
+                let (key, quoted_value) = line.split_once('=').unwrap();
+                let mut value = String::new();
+                let mut chars = quoted_value.strip_prefix('"').unwrap().strip_suffix('"').unwrap().chars();
+
+                while let Some(c) = chars.next() {
+                    if c != '\\' {
+                        value.push(c);
+                        continue;
+                    }
+                    value.push(match chars.next().unwrap() {
+                        'n' => '\n',
+                        'r' => '\r',
+                        escaped => escaped,
+                    });
+                }
+
+                (key.to_string(), value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dotenv_outputs_round_trip_to_the_github_outputs() {
+        let _lock = test_repo::lock_global_settings();
+        let test_repo = test_repo::TestRepo::new();
+        let github_output = test_repo.dir.join("github_output").to_string_lossy().to_string();
+        let dotenv_file = test_repo.dir.join("outputs.env").to_string_lossy().to_string();
+        let outputs = [
+            ("all_changed_files", "src/a.txt\nsrc/b.txt\n"),
+            ("added_files", "windows.txt\r\nunix.txt"),
+            ("modified_files", "$HOME/${USER}.txt"),
+            ("deleted_files", "`id`.txt $(id).txt"),
+            ("renamed_files", "back\\slash \"quoted\".txt"),
+            ("any_changed", "true"),
+        ];
+
+        for (output_target, output_dotenv_file) in [(OutputTarget::Github, ""), (OutputTarget::Dotenv, dotenv_file.as_str())] {
+            set_output_target(&output_target, output_dotenv_file);
+            for (key, value) in outputs {
+                crate::utils::set_output(&github_output, "", key, value);
+            }
+        }
+        set_output_target(&OutputTarget::Github, "");
+
+        let github_outputs: Vec<(String, String)> = parse_github_output(&std::fs::read_to_string(&github_output).unwrap())
+            .into_iter()
+            .map(|(key, value)| (key.to_uppercase(), value))
+            .collect();
+        let dotenv_outputs = parse_dotenv_output(&std::fs::read_to_string(&dotenv_file).unwrap());
+
+        assert_eq!(dotenv_outputs, github_outputs);
+        assert_eq!(dotenv_outputs.len(), outputs.len());
+        assert_eq!(dotenv_outputs[1], ("ADDED_FILES".to_string(), "windows.txt\r\nunix.txt".to_string()));
+    }
+}
\ No newline at end of file
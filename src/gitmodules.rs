@@ -0,0 +1,101 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use git2::{Commit, Config, Repository};
+
+use crate::signals;
+use crate::temp;
+
+// Submodule settings compared between the commits
+const SUBMODULE_CONFIG_KEYS: [&str; 3] = ["url", "branch", "path"];
+
+// Utility function to read the submodule settings of `.gitmodules` in a commit, keyed by submodule name.
+// git2 only parses config files, so the blob is written to a temporary file first.
+pub fn read_gitmodules(repo: &Repository, commit: &Commit, label: &str) -> BTreeMap<String, BTreeMap<String, String>> {
+    let mut submodules: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+
+    let blob = match commit
+        .tree()
+        .and_then(|tree| tree.get_path(Path::new(".gitmodules")))
+        .and_then(|entry| entry.to_object(repo))
+        .and_then(|object| object.peel_to_blob())
+    {
+        Ok(blob) => blob,
+        Err(_) => return submodules,
+    };
+
+    let config_path = match temp::get_path(&format!("gitmodules-{}", label)) {
+        Ok(config_path) => config_path,
+        Err(e) => {
+            warn_or_fail!("Unable to create the temporary directory: {}", e);
+            return submodules;
+        }
+    };
+    signals::register_temp_file(&config_path);
+    if let Err(e) = fs::write(&config_path, blob.content()) {
+        warn_or_fail!("Unable to read .gitmodules of {}: {}", commit.id(), e);
+        return submodules;
+    }
+
+    match Config::open(&config_path) {
+        Ok(config) => {
+            let mut entries = config.entries(Some("submodule\\..*")).unwrap();
+            while let Some(Ok(entry)) = entries.next() {
+                let (name, value) = match (entry.name(), entry.value()) {
+                    (Some(name), Some(value)) => (name, value),
+                    _ => continue,
+                };
+
+                // `submodule.<name>.<key>`, the name itself may contain dots
+                if let Some((name, key)) = name.trim_start_matches("submodule.").rsplit_once('.') {
+                    if SUBMODULE_CONFIG_KEYS.contains(&key) {
+                        submodules.entry(name.to_string()).or_default().insert(key.to_string(), value.to_string());
+                    }
+                }
+            }
+        }
+        Err(e) => warn_or_fail!("Unable to parse .gitmodules of {}: {}", commit.id(), e),
+    }
+
+    let _ = fs::remove_file(&config_path);
+    signals::unregister_temp_file(&config_path);
+
+    submodules
+}
+
+// Utility function to compare the submodule settings of both commits, returns a JSON object keyed by submodule
+// name with the changed settings e.g `{"lib": {"url": {"previous": "...", "current": "..."}}}`. Added and removed
+// submodules have an empty previous or current value.
+pub fn get_submodule_config_changes(repo: &Repository, previous_commit: &Commit, current_commit: &Commit) -> serde_json::Map<String, serde_json::Value> {
+    let previous_submodules = read_gitmodules(repo, previous_commit, "previous");
+    let current_submodules = read_gitmodules(repo, current_commit, "current");
+    let names: BTreeSet<&String> = previous_submodules.keys().chain(current_submodules.keys()).collect();
+
+    let empty: BTreeMap<String, String> = BTreeMap::new();
+    let mut changes = serde_json::Map::new();
+
+    for name in names {
+        let previous_config = previous_submodules.get(name).unwrap_or(&empty);
+        let current_config = current_submodules.get(name).unwrap_or(&empty);
+        let mut submodule_changes = serde_json::Map::new();
+
+        for key in SUBMODULE_CONFIG_KEYS {
+            let previous_value = previous_config.get(key).map(|value| value.as_str()).unwrap_or_default();
+            let current_value = current_config.get(key).map(|value| value.as_str()).unwrap_or_default();
+
+            if previous_value != current_value {
+                submodule_changes.insert(
+                    key.to_string(),
+                    serde_json::json!({ "previous": previous_value, "current": current_value }),
+                );
+            }
+        }
+
+        if !submodule_changes.is_empty() {
+            changes.insert(name.clone(), serde_json::Value::Object(submodule_changes));
+        }
+    }
+
+    changes
+}
@@ -0,0 +1,62 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Suppresses the informational output, warnings, errors and outputs are always printed
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: &bool) {
+    QUIET.store(*quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+// Prints the `::debug::` messages without the annotation so they're shown without step debug logging
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_verbose(verbose: &bool) {
+    VERBOSE.store(*verbose, Ordering::Relaxed);
+}
+
+pub fn is_verbose() -> bool {
+    VERBOSE.load(Ordering::Relaxed)
+}
+
+// Treats every warning as an error
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+pub fn set_strict(strict: &bool) {
+    STRICT.store(*strict, Ordering::Relaxed);
+}
+
+pub fn is_strict() -> bool {
+    STRICT.load(Ordering::Relaxed)
+}
+
+// Print informational output e.g progress, `::debug::` messages and the log group markers unless `quiet` is set,
+// with `verbose` the `::debug::` messages are printed as plain lines.
+// `::error::` annotations use `println!` directly and warnings use `warn_or_fail!`.
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if !$crate::log::is_quiet() {
+            let message = format!($($arg)*);
+            match message.strip_prefix("::debug::") {
+                Some(debug_message) if $crate::log::is_verbose() => println!("{}", debug_message),
+                _ => println!("{}", message),
+            }
+        }
+    };
+}
+
+// Print a `::warning::` annotation. With `strict` set the warning is printed as an `::error::` annotation instead
+// and the run fails, the outputs computed so far are kept and `failed`/`failure_reason` are written.
+macro_rules! warn_or_fail {
+    ($($arg:tt)*) => {
+        if $crate::log::is_strict() {
+            println!("::error::{}", format!($($arg)*));
+            $crate::error::fail($crate::error::FailureReason::StrictWarning);
+        } else {
+            println!("::warning::{}", format!($($arg)*));
+        }
+    };
+}
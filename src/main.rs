@@ -1,33 +1,156 @@
+// Declared first, the `log!` macro is used by every other module
+#[macro_use]
+mod log;
+
 mod args;
+mod co_changes;
+mod codeowners;
+mod defaults;
+mod error;
+mod filters;
+mod gitattributes;
+mod github_output;
+mod gitmodules;
+mod ranges;
+mod record;
+mod recover;
+mod report;
+mod signals;
+mod state;
+mod temp;
+#[cfg(test)]
+mod test_repo;
 mod utils;
 
+use std::collections::BTreeMap;
+
 use clap::Parser;
 
-use git2::{Commit, Config, Repository};
-use json2file::{writer};
+use git2::Commit;
 
-use crate::args::Args;
+use crate::args::{Args, SecretString};
+use crate::error::FailureReason;
 use crate::utils::DiffType;
 
 fn main() {
-    let args: Args = args::Args::parse();
-    let git_version = utils::git_version();
+    let total_timer = utils::Timer::start();
+    let mut args: Args = args::Args::parse();
+    args.resolve_file_references();
+
+    log::set_quiet(&args.quiet);
+    log::set_verbose(&args.verbose);
+    log::set_strict(&args.strict);
+    error::set_fail_fast(&args.fail_fast);
+    signals::install_handler();
+
+    // Fall back to the token provided by the environment
+    if args.token.is_empty() {
+        let token = ["GITHUB_TOKEN", "GH_TOKEN"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|token| !token.is_empty())
+            .unwrap_or_default();
+        args.token = SecretString::from(token);
+    }
+
+    if args.print_config {
+        println!("{}", serde_json::to_string_pretty(&args.effective_config()).unwrap());
+        std::process::exit(0);
+    }
 
-    println!("::group::changed-files-diff-sha");
+    if args.print_schema {
+        println!("{}", report::get_schema());
+        std::process::exit(0);
+    }
+
+    // Exits with `1` when the changed files differ like `diff`
+    if !args.compare_reports.is_empty() {
+        let comparison = report::compare_reports(
+            &report::read_report(&args.compare_reports[0]),
+            &report::read_report(&args.compare_reports[1]),
+        );
+
+        if args.json {
+            println!("{}", serde_json::to_string_pretty(&comparison).unwrap());
+        } else {
+            println!("{}", report::format_comparison(&comparison));
+        }
+        std::process::exit(if comparison.is_empty() { 0 } else { 1 });
+    }
 
-    if utils::version_number(&git_version) < utils::version_number("2.18.0") {
+    temp::init(&args.temp_dir, &args.keep_temp);
+
+    if !args.record.is_empty() {
+        record::enable(&args.record, &utils::get_event_path(&args.event_json), args.token.expose(), &args.record_redact);
+    }
+
+    if !args.replay.is_empty() {
+        record::enable_replay(&args.replay);
+    }
+
+    utils::set_no_subprocess(&args.no_subprocess);
+    let git_version = if args.no_subprocess { String::new() } else { utils::git_version() };
+
+    if utils::is_valid_output_prefix(&args.output_prefix) {
+        error::set_output_prefix(&args.output_prefix);
+    } else {
+        error::invalid(
+            &format!(
+                "Invalid output_prefix: '{}'. Only letters, digits and underscores are allowed and it must not start with a digit",
+                args.output_prefix
+            ),
+            FailureReason::InvalidInput,
+        );
+    }
+
+    log!("::group::changed-files-diff-sha");
+
+    if args.no_subprocess {
+        log!("::debug::Skipping the git version check, `no_subprocess` is set");
+    } else if utils::version_number(&git_version) < utils::version_number("2.18.0") {
         println!(
             "::error::Invalid git version. Please upgrade ({}) to >= (2.18.0)",
             git_version
         );
-        std::process::exit(1);
+        error::fail(FailureReason::GitUnavailable);
     } else {
-        println!("Valid git version found: ({})", git_version);
+        log!("Valid git version found: ({})", git_version);
+    }
+
+    github_output::set_output_compat(&args.output_compat);
+    github_output::set_output_target(&args.output_target, &args.output_dotenv_file);
+    github_output::set_requested_outputs(&args.outputs, &args.output_prefix);
+
+    if args.shard_count == 0 || args.shard_index >= args.shard_count {
+        error::invalid(
+            &format!(
+                "Invalid shard: shard_index ({}) must be lower than shard_count ({}) and shard_count must be at least 1",
+                args.shard_index, args.shard_count
+            ),
+            FailureReason::InvalidInput,
+        );
+    }
+
+    if !args.single_output.is_empty() {
+        if args.single_output != "json" {
+            error::invalid(&format!("Invalid single_output: '{}'. Use 'json'", args.single_output), FailureReason::InvalidInput);
+        }
+
+        // The lists are embedded in the document as JSON
+        args.json = true;
+        args.json_raw_format = true;
+        report::enable(&args.output_prefix);
+    }
+    utils::set_lock_retry_seconds(&args.lock_retry_seconds);
+
+    let is_local_mode = !utils::is_github_actions();
+    if is_local_mode {
+        log!("::debug::Not running in GitHub Actions, using local mode");
     }
 
     let (
         github_workspace,
-        github_output,
+        mut github_output,
         github_ref,
         github_event_base_ref,
         github_event_head_repo_fork,
@@ -35,55 +158,428 @@ fn main() {
         github_event_pull_request_base_ref,
         github_event_pull_request_head_ref,
         github_event_pull_request_base_sha,
+        github_event_pull_request_head_sha,
+        github_event_pull_request_head_repo_clone_url,
         github_refname,
         github_event_before,
-        github_event_forced
-    ) = utils::get_env_vars();
+        github_event_after,
+        github_event_forced,
+        github_sha,
+    ) = utils::get_env_vars(&args);
+
+    if is_local_mode {
+        // Outputs are printed to stdout
+        github_output = String::new();
+    }
 
-    // join the workspace path with the args.path
-    let path = std::path::Path::new(&github_workspace).join(&args.path);
-    let repo = utils::get_repo(&path);
+    utils::set_output(
+        &github_output,
+        &args.output_prefix,
+        "effective_config",
+        &utils::format_json_output(&args.effective_config().to_string(), &args.json_raw_format),
+    );
 
-    let mut config = Config::open_default().unwrap();
+    let ranges = ranges::parse_ranges(&args.ranges);
 
-    let quotepath_value = if args.quotepath == "false" { "off" } else { "on" };
-    println!("::debug::quotepath: {}", quotepath_value);
-    config.set_str("core.quotepath", quotepath_value).unwrap();
+    if !args.pull_request_number.is_empty() {
+        if !args.pull_request_number.chars().all(|c| c.is_ascii_digit()) {
+            error::invalid(&format!("Invalid pull_request_number: '{}'", args.pull_request_number), FailureReason::InvalidInput);
+        }
+
+        if args.base_ref.is_empty() && args.base_sha.is_empty() {
+            error::invalid(
+                "`pull_request_number` requires `base_ref` or `base_sha` to be set, the base branch can't be read from the event",
+                FailureReason::InvalidInput,
+            );
+        }
+    }
+
+    let between_tags = if args.between_tags.is_empty() {
+        None
+    } else {
+        match args.between_tags.split_once("..") {
+            Some((base_tag, head_tag)) if !base_tag.is_empty() && !head_tag.is_empty() && !head_tag.starts_with('.') => {
+                Some((base_tag.to_string(), head_tag.to_string()))
+            }
+            _ => {
+                error::invalid(
+                    &format!("Invalid between_tags: '{}'. Use `<base tag>..<head tag>` e.g `v1.4.0..v1.5.0`", args.between_tags),
+                    FailureReason::InvalidInput,
+                );
+                None
+            }
+        }
+    };
+
+    let range = if args.range.is_empty() {
+        None
+    } else {
+        match utils::parse_range(&args.range) {
+            Some(range) => Some(range),
+            None => {
+                error::invalid(
+                    &format!("Invalid range: '{}'. Use `<base>..<head>` or `<base>...<head>` e.g `origin/main...HEAD`", args.range),
+                    FailureReason::InvalidInput,
+                );
+                None
+            }
+        }
+    };
+
+    let co_change_rules = co_changes::parse_co_change_rules(&args.require_co_changes);
+
+    if args.per_commit_files && !args.json {
+        error::invalid("`per_commit_files` requires `json` to be set to `true`", FailureReason::InvalidInput);
+    }
+
+    if args.output_churn && !args.json {
+        error::invalid("`output_churn` requires `json` to be set to `true`", FailureReason::InvalidInput);
+    }
+
+    if args.output_dir_stats && !args.json {
+        error::invalid("`output_dir_stats` requires `json` to be set to `true`", FailureReason::InvalidInput);
+    }
+
+    let glob_patterns = utils::get_glob_patterns(
+        &args.files,
+        &args.files_separator,
+        &args.files_from_source_file,
+        &args.files_from_source_file_separator,
+        &args.files_ignore,
+        &args.files_ignore_separator,
+        &args.files_ignore_from_source_file,
+        &args.files_ignore_from_source_file_separator,
+        &args.path,
+        &args.max_patterns,
+    );
+
+    let prune_paths = utils::set_prune_paths(&args.prune_paths);
+    for (prune_path, pattern) in utils::get_pruned_patterns(&prune_paths, &glob_patterns) {
+        warn_or_fail!(
+            "The prune path {} overlaps the files pattern {}, the matching files beneath {} are never reported",
+            prune_path, pattern, prune_path
+        );
+    }
+
+    // Every problem with the inputs is reported at once before the commits are resolved
+    error::exit_on_diagnostics();
+
+    let repo = if is_local_mode {
+        utils::discover_repo(std::path::Path::new(&args.path))
+    } else {
+        // join the workspace path with the args.path
+        let path = std::path::Path::new(&github_workspace).join(&args.path);
+        let repo = utils::get_repo(&path);
+
+        // The event refers to commits of the superproject, which don't exist in the submodule
+        if let Some((superproject_path, submodule_path)) = utils::get_superproject(&repo) {
+            println!(
+                "::error::`path` ({}) is the submodule {} of the repository at {}. The commits of the event belong to that repository, run against it with `files: {}/**` instead.",
+                args.path,
+                submodule_path,
+                superproject_path.display(),
+                submodule_path
+            );
+            error::fail(FailureReason::InvalidInput);
+        }
+
+        repo
+    };
+
+    github_output::set_output_size_limit(&args.max_github_output_bytes, utils::get_workdir(&repo).join(&args.output_dir));
+
+    if args.no_subprocess {
+        // Everything that still needs git is rejected before any commit is resolved
+        let mut git_requirements: Vec<&str> = Vec::new();
+
+        if repo.is_shallow() {
+            git_requirements.push("a shallow clone, the history can only be fetched with git (check out the full history e.g `fetch-depth: 0`)");
+        }
+        if !args.pull_request_number.is_empty() {
+            git_requirements.push("`pull_request_number`, the pull request can only be fetched with git");
+        }
+        if !args.since.is_empty() && utils::parse_timestamp_utc(&args.since).is_none() {
+            git_requirements.push("`since` other than an ISO-8601 date e.g `2024-01-31T12:00:00Z`");
+        }
+        if !args.until.is_empty() && utils::parse_timestamp_utc(&args.until).is_none() {
+            git_requirements.push("`until` other than an ISO-8601 date e.g `2024-01-31T12:00:00Z`");
+        }
+
+        if !git_requirements.is_empty() {
+            error::invalid(
+                &format!("`no_subprocess` can't be used with: {}", git_requirements.join("; ")),
+                FailureReason::InvalidInput,
+            );
+            error::exit_on_diagnostics();
+        }
+    }
+
+    // Without a HEAD the current commit has to be known upfront
+    if let Some(current_sha) = utils::get_unborn_head_sha(&args.sha, &github_sha, &repo) {
+        args.sha = current_sha;
+    }
+
+    let (remote_url, repository_root, head_ref_resolved) = utils::get_repository_provenance(&repo);
+    utils::set_output(&github_output, &args.output_prefix, "remote_url", &remote_url);
+    utils::set_output(&github_output, &args.output_prefix, "repository_root", &repository_root);
+    utils::set_output(&github_output, &args.output_prefix, "head_ref_resolved", &head_ref_resolved);
+
+    // Snapshot of the remote-tracking refs before any fetch, compared once every commit is resolved
+    let remote_refs_before = utils::get_remote_refs(&repo);
+
+    let filters = filters::read_filters(&args.filters_from_files, &repo, &args.max_patterns);
+
+    // Paths are quoted by us when writing the outputs since libgit2 ignores `core.quotepath`
+    let quotepath = args.quotepath != "false";
+    log!("::debug::quotepath: {}", quotepath);
 
     if !args.diff_relative.is_empty() {
-        println!("::debug::diff_relative: true");
-        config.set_str("diff.relative", &args.diff_relative).unwrap();
+        log!("::debug::diff_relative: {}", args.diff_relative);
+        utils::set_diff_relative(&args.diff_relative);
+    }
+
+    let replace_refs_count = utils::get_replace_refs_count(&repo);
+    log!("::debug::replace refs: {}", replace_refs_count);
+
+    if args.no_replace_objects {
+        // Inherited by every git subprocess
+        std::env::set_var("GIT_NO_REPLACE_OBJECTS", "1");
+    } else if replace_refs_count > 0 {
+        warn_or_fail!(
+            "Found {} replace refs. The diff is computed using libgit2 which ignores replace refs while git commands apply them, set `no_replace_objects` to ignore them consistently.",
+            replace_refs_count
+        );
     }
 
     let submodules = repo.submodules().unwrap();
-    let has_submodules = submodules.len() > 0;
+    let has_submodules = !submodules.is_empty();
+    utils::set_include_submodule_paths(&args.include_submodule_paths);
+
+    let fetch_depth = utils::get_fetch_depth(&args.fetch_depth, &args.fetch_full_history);
+    log!("::debug::fetch_depth: {}", fetch_depth);
 
     let is_shallow_clone = repo.is_shallow();
-    println!("::debug::is_shallow_clone: {}", is_shallow_clone);
+    log!("::debug::is_shallow_clone: {}", is_shallow_clone);
 
-    let mut current_commit: git2::Commit = Commit::default();
-    let mut previous_commit: git2::Commit = Commit::default();
+    let current_commit: Commit;
+    let previous_commit: Commit;
     let mut diff : String = "..".to_string();
     let mut is_tag = false;
-    let mut extra_args = "--no-tags --prune --recurse-submodules";
     let mut source_branch = String::new();
-    let mut initial_commit = false;
+    let initial_commit: bool;
+    let mut unrelated_histories = false;
 
-    if github_ref.starts_with("refs/tags/") {
+    if github_ref.starts_with("refs/tags/") || between_tags.is_some() {
         is_tag = true;
-        extra_args = "--prune --no-recurse-submodules";
         source_branch = github_event_base_ref.replace("refs/heads/", "");
 
-        println!("::debug::is_tag: {}", is_tag);
-        println!("::debug::source_branch: {}", source_branch);
+        log!("::debug::is_tag: {}", is_tag);
+        log!("::debug::source_branch: {}", source_branch);
+    }
+
+    let fetch_submodule_history = args.fetch_submodule_history && has_submodules;
+    let extra_args = utils::get_fetch_extra_args(&is_tag, &fetch_submodule_history);
+    log!("::debug::extra_args: {}", extra_args.join(" "));
+
+    // The event commit wins over a mismatching checkout unless `trust_checkout` is set
+    let checkout_mismatch = !is_local_mode
+        && utils::is_checkout_mismatch(&github_sha, &github_ref, &github_event_pull_request_head_sha, &repo);
+    let mut use_head = args.use_head;
+    let mut sha = args.sha.clone();
+
+    if checkout_mismatch {
+        if args.trust_checkout {
+            warn_or_fail!("Using the checked out HEAD as the current commit (`trust_checkout`)");
+            use_head = !args.use_event_after;
+        } else if sha.is_empty() && !github_event_pull_request_base_ref.is_empty() {
+            // Pull requests use HEAD as the current commit, use the pull request head instead
+            sha = if github_event_pull_request_head_sha.is_empty() {
+                github_sha.clone()
+            } else {
+                github_event_pull_request_head_sha.clone()
+            };
+            utils::fetch_commit_if_missing("the event commit", &sha, &fetch_depth, &args.token, &repo);
+            warn_or_fail!("Using the event commit {} as the current commit, set `trust_checkout` to use the checked out HEAD instead", sha);
+        } else if sha.is_empty() {
+            warn_or_fail!("Using GITHUB_SHA as the current commit, set `trust_checkout` to use the checked out HEAD instead");
+        }
     }
 
-    println!("::debug::extra_args: {}", extra_args);
+    utils::set_output(&github_output, &args.output_prefix, "checkout_mismatch", &checkout_mismatch.to_string());
+
+    // Comparing the default branch with itself would be empty, the event is used instead
+    let default_branch = if args.base_default_branch {
+        let default_branch = utils::get_default_branch(&utils::get_event_path(&args.event_json), &repo);
+        if default_branch.is_empty() {
+            println!("::error::Unable to determine the default branch: the event payload has no `repository.default_branch` and refs/remotes/origin/HEAD doesn't exist. Set `base_ref` to the default branch instead.");
+            error::fail(FailureReason::InvalidInput);
+        }
+
+        utils::set_output(&github_output, &args.output_prefix, "default_branch", &default_branch);
+        default_branch
+    } else {
+        String::new()
+    };
+    let compare_default_branch = !default_branch.is_empty()
+        && (!github_event_pull_request_base_ref.is_empty() || github_refname != default_branch);
+    if args.base_default_branch && !compare_default_branch {
+        log!("::debug::The current branch is the default branch {}, comparing the commits of the event", default_branch);
+    }
+
+    let base_strategy = utils::select_base_strategy(&utils::BaseStrategyOptions {
+        base_sha: &args.base_sha,
+        base_ref: &args.base_ref,
+        since: &args.since,
+        since_last_remote_commit: args.since_last_remote_commit,
+    });
+    log!("::debug::Base strategy: {}", base_strategy.as_str());
+    utils::set_output(&github_output, &args.output_prefix, "base_strategy", base_strategy.as_str());
+
+    let sha_resolution_timer = utils::Timer::start();
+
+    // The commits resolved by a previous invocation in the same job skip the fetches and the resolution
+    let state_file = state::get_state_file(&args, &repo);
+    let fingerprint = state::get_fingerprint(
+        &args,
+        &state::ResolvedEvent {
+            github_ref: &github_ref,
+            github_refname: &github_refname,
+            github_sha: &github_sha,
+            github_event_base_ref: &github_event_base_ref,
+            github_event_before: &github_event_before,
+            github_event_after: &github_event_after,
+            github_event_forced,
+            github_event_pull_request_base_ref: &github_event_pull_request_base_ref,
+            github_event_pull_request_base_sha: &github_event_pull_request_base_sha,
+            github_event_pull_request_head_sha: &github_event_pull_request_head_sha,
+            sha: &sha,
+            use_head,
+            default_branch: &default_branch,
+        },
+        &repo,
+    );
+    let cached_state = state::read_state(&state_file, &fingerprint, &repo);
+
+    if let Some(cached_state) = &cached_state {
+        previous_commit = repo.find_commit(git2::Oid::from_str(&cached_state.previous_sha).unwrap()).unwrap();
+        current_commit = repo.find_commit(git2::Oid::from_str(&cached_state.current_sha).unwrap()).unwrap();
+        diff = cached_state.diff.clone();
+        unrelated_histories = cached_state.unrelated_histories;
+    } else if let Some((base_tag, head_tag)) = &between_tags {
+        previous_commit = utils::get_tag_commit(base_tag, &extra_args, &fetch_depth, &args.token, &repo);
+        current_commit = utils::get_tag_commit(head_tag, &extra_args, &fetch_depth, &args.token, &repo);
+
+        log!("::debug::Comparing tags {} ({})..{} ({})", base_tag, previous_commit.id(), head_tag, current_commit.id());
+    } else if let Some((base_rev, range_diff, head_rev)) = &range {
+        previous_commit = utils::get_range_commit(base_rev, &fetch_depth, &args.token, &repo);
+        current_commit = utils::get_range_commit(head_rev, &fetch_depth, &args.token, &repo);
+        diff = range_diff.clone();
+
+        if diff == "..." && repo.merge_base(previous_commit.id(), current_commit.id()).is_err() {
+            println!(
+                "::error::Unable to find a merge base between {} ({}) and {} ({}). Increase the fetch_depth to a number higher than {}.",
+                base_rev,
+                previous_commit.id(),
+                head_rev,
+                current_commit.id(),
+                fetch_depth
+            );
+            error::fail(FailureReason::NoMergeBase);
+        }
+
+        log!("::debug::Comparing range {} ({}){}{} ({})", base_rev, previous_commit.id(), diff, head_rev, current_commit.id());
+    } else if !args.pull_request_number.is_empty() {
+        // Resolved like a pull request event using the fetched pull request head
+        let pull_request_head_sha = utils::fetch_pull_request(
+            &args.pull_request_number,
+            &args.base_ref,
+            &fetch_depth,
+            &args.token,
+            &repo,
+        );
+
+        (
+            previous_commit,
+            current_commit,
+            diff,
+            unrelated_histories,
+        ) = utils::get_previous_and_current_sha_for_pull_request_event(
+            &utils::PullRequest {
+                number: &args.pull_request_number,
+                base_ref: &args.base_ref,
+                head_ref: &format!("pull/{}/head", args.pull_request_number),
+                head_sha: &pull_request_head_sha,
+                ..Default::default()
+            },
+            &utils::PullRequestShaOptions {
+                extra_args: &extra_args,
+                fetch_submodule_history,
+                fetch_depth,
+                is_shallow_clone,
+                until: &args.until,
+                since: &args.since,
+                sha: &pull_request_head_sha,
+                base_sha: &args.base_sha,
+                // There's no event payload to get the last remote commit from
+                base_strategy: if base_strategy == utils::BaseStrategy::SinceLastRemoteCommit { utils::BaseStrategy::Event } else { base_strategy },
+                token: &args.token,
+            },
+            &repo,
+        );
+    } else if compare_default_branch {
+        let current_rev = if !sha.is_empty() {
+            sha.clone()
+        } else if !github_sha.is_empty() && !use_head {
+            github_sha.clone()
+        } else {
+            "HEAD".to_string()
+        };
+
+        previous_commit = utils::get_default_branch_commit(&default_branch, &fetch_depth, &args.token, &repo);
+        current_commit = utils::get_range_commit(&current_rev, &fetch_depth, &args.token, &repo);
+        diff = "...".to_string();
+
+        if repo.merge_base(previous_commit.id(), current_commit.id()).is_err() {
+            println!(
+                "::error::Unable to find a merge base between the default branch {} ({}) and {} ({}). Increase the fetch_depth to a number higher than {}.",
+                default_branch,
+                previous_commit.id(),
+                current_rev,
+                current_commit.id(),
+                fetch_depth
+            );
+            error::fail(FailureReason::NoMergeBase);
+        }
 
-    if github_event_pull_request_base_ref.is_empty() {
+        log!("::debug::Comparing the default branch {} ({})...{} ({})", default_branch, previous_commit.id(), current_rev, current_commit.id());
+    } else if base_strategy == utils::BaseStrategy::BaseRef && !is_local_mode {
+        // An explicit base takes precedence over the event
+        let current_rev = if !sha.is_empty() {
+            sha.clone()
+        } else if !github_sha.is_empty() && !use_head {
+            github_sha.clone()
+        } else {
+            "HEAD".to_string()
+        };
+
+        previous_commit = utils::get_range_commit(&args.base_ref, &fetch_depth, &args.token, &repo);
+        current_commit = utils::get_range_commit(&current_rev, &fetch_depth, &args.token, &repo);
+
+        log!("::debug::Comparing base_ref {} ({})..{} ({})", args.base_ref, previous_commit.id(), current_rev, current_commit.id());
+    } else if is_local_mode {
         (
+            previous_commit,
             current_commit,
+        ) = utils::get_previous_and_current_sha_for_local_mode(
+            &args.base_sha,
+            &args.base_ref,
+            &sha,
+            &repo,
+        );
+    } else if github_event_pull_request_base_ref.is_empty() {
+        (
             previous_commit,
+            current_commit,
             initial_commit,
         ) = utils::get_previous_and_current_sha_for_push_event(
             &extra_args,
@@ -92,130 +588,212 @@ fn main() {
             &github_refname,
             &github_event_forced,
             &github_event_before,
+            &github_event_after,
             &source_branch,
-            &has_submodules,
-            &args.fetch_depth,
+            &fetch_submodule_history,
+            &fetch_depth,
             &args.until,
             &args.since,
-            &args.sha,
+            &sha,
             &args.base_sha,
-            &args.since_last_remote_commit,
+            &base_strategy,
+            &github_sha,
+            &use_head,
+            &args.use_event_after,
+            &args.token,
             &repo,
         );
 
         if initial_commit {
-            println!("Initial commit detected, skipping...");
+            log!("Initial commit detected, skipping...");
+            report::write(&github_output, &args.output_prefix);
+            record::save();
+            temp::cleanup(false);
             std::process::exit(0);
         }
     } else {
         (
-            current_commit,
             previous_commit,
-            diff
+            current_commit,
+            diff,
+            unrelated_histories,
         ) = utils::get_previous_and_current_sha_for_pull_request_event(
-            &extra_args,
-            &github_event_before,
-            &github_event_pull_request_base_ref,
-            &github_event_pull_request_head_ref,
-            &github_event_head_repo_fork,
-            &github_event_pull_request_number,
-            &github_event_pull_request_base_sha,
-            &has_submodules,
-            &args.fetch_depth,
-            &is_shallow_clone,
-            &args.since,
-            &args.sha,
-            &args.base_sha,
-            &args.since_last_remote_commit,
+            &utils::PullRequest {
+                number: &github_event_pull_request_number,
+                base_ref: &github_event_pull_request_base_ref,
+                head_ref: &github_event_pull_request_head_ref,
+                base_sha: &github_event_pull_request_base_sha,
+                head_sha: &github_event_pull_request_head_sha,
+                before: &github_event_before,
+                head_repo_fork: github_event_head_repo_fork == "true",
+                head_repo_clone_url: &github_event_pull_request_head_repo_clone_url,
+            },
+            &utils::PullRequestShaOptions {
+                extra_args: &extra_args,
+                fetch_submodule_history,
+                fetch_depth,
+                is_shallow_clone,
+                until: &args.until,
+                since: &args.since,
+                sha: &sha,
+                base_sha: &args.base_sha,
+                base_strategy,
+                token: &args.token,
+            },
             &repo,
         );
     }
 
-    let glob_patterns = utils::get_glob_patterns(
-        &args.files,
-        &args.files_separator,
-        &args.files_from_source_file,
-        &args.files_from_source_file_separator,
-        &args.files_ignore,
-        &args.files_ignore_separator,
-        &args.files_ignore_from_source_file,
-        &args.files_ignore_from_source_file_separator,
-        &args.path,
-    );
+    // Fetches are reported separately
+    let sha_resolution_duration = sha_resolution_timer.elapsed().saturating_sub(utils::get_fetch_duration());
 
-    let added_files = utils::get_diff(
-        &repo,
-        &previous_commit,
-        &current_commit,
-        &[DiffType::Added],
-        &diff,
-        &glob_patterns,
-    );
+    if cached_state.is_none() {
+        state::write_state(
+            &state_file,
+            &state::State {
+                fingerprint,
+                previous_sha: previous_commit.id().to_string(),
+                current_sha: current_commit.id().to_string(),
+                diff: diff.clone(),
+                unrelated_histories,
+                is_shallow: repo.is_shallow(),
+            },
+        );
+    }
 
-    let copied_files = utils::get_diff(
-        &repo,
-        &previous_commit,
-        &current_commit,
-        &[DiffType::Copied],
-        &diff,
-        &glob_patterns,
-    );
+    report::set_commits(&previous_commit.id().to_string(), &current_commit.id().to_string(), &diff);
 
-    let deleted_files = utils::get_diff(
-        &repo,
-        &previous_commit,
-        &current_commit,
-        &[DiffType::Deleted],
-        &diff,
-        &glob_patterns,
-    );
+    record::record_decision("previous_sha", &previous_commit.id().to_string());
+    record::record_decision("current_sha", &current_commit.id().to_string());
+    record::record_decision("diff", &diff);
+    record::record_decision("unrelated_histories", &unrelated_histories.to_string());
 
-    let modified_files = utils::get_diff(
-        &repo,
-        &previous_commit,
-        &current_commit,
-        &[DiffType::Modified],
-        &diff,
-        &glob_patterns,
-    );
+    if let Some((base_tag, head_tag)) = &between_tags {
+        utils::set_output(&github_output, &args.output_prefix, "base_tag", base_tag);
+        utils::set_output(&github_output, &args.output_prefix, "head_tag", head_tag);
+    }
 
-    let renamed_files = utils::get_diff(
-        &repo,
-        &previous_commit,
-        &current_commit,
-        &[DiffType::Renamed],
-        &diff,
-        &glob_patterns,
-    );
+    if github_output::is_requested("commits_count") || github_output::is_requested("behind_count") {
+        // Counted from the resolved commits, a shallow history only counts the fetched commits
+        let (commits_count, mut commits_count_exceeded) = utils::count_commits(
+            &repo,
+            current_commit.id(),
+            previous_commit.id(),
+            &args.count_first_parent_only,
+            &args.max_commits_count,
+        );
+        utils::set_output(&github_output, &args.output_prefix, "commits_count", &commits_count.to_string());
 
-    let type_changed_files = utils::get_diff(
-        &repo,
-        &previous_commit,
-        &current_commit,
-        &[DiffType::TypeChanged],
-        &diff,
-        &glob_patterns,
-    );
+        if diff == "..." {
+            let (behind_count, behind_count_exceeded) = utils::count_commits(
+                &repo,
+                previous_commit.id(),
+                current_commit.id(),
+                &args.count_first_parent_only,
+                &args.max_commits_count,
+            );
+            commits_count_exceeded = commits_count_exceeded || behind_count_exceeded;
+            utils::set_output(&github_output, &args.output_prefix, "behind_count", &behind_count.to_string());
+        }
+
+        utils::set_output(&github_output, &args.output_prefix, "commits_count_exceeded", &commits_count_exceeded.to_string());
+    }
 
-    let unmerged_files = utils::get_diff(
+    utils::set_output(&github_output, &args.output_prefix, "unrelated_histories", &unrelated_histories.to_string());
+    utils::set_output(&github_output, &args.output_prefix, "stale_event_data", &utils::is_stale_event_data().to_string());
+
+    if unrelated_histories && args.fail_on_unrelated_histories {
+        println!(
+            "::error::The previous commit ({}) and the current commit ({}) have unrelated histories.",
+            previous_commit.id(),
+            current_commit.id()
+        );
+        error::fail(FailureReason::UnrelatedHistories);
+    }
+
+    ranges::write_ranges_outputs(
         &repo,
-        &previous_commit,
+        &ranges,
         &current_commit,
-        &[DiffType::Unmerged],
         &diff,
         &glob_patterns,
+        &fetch_depth,
+        &github_output,
+        &args,
     );
 
-    let unknown_files = utils::get_diff(
-        &repo,
-        &previous_commit,
-        &current_commit,
-        &[DiffType::Unknown],
-        &diff,
-        &glob_patterns,
+    let ref_updates = utils::get_ref_updates(&remote_refs_before, &utils::get_remote_refs(&repo));
+    for (name, before_oid, after_oid) in &ref_updates {
+        if args.warn_on_ref_updates {
+            warn_or_fail!("The fetch moved {} from {} to {}, earlier steps of the job may have seen the previous commit", name, before_oid, after_oid);
+        } else {
+            log!("::debug::The fetch moved {} from {} to {}", name, before_oid, after_oid);
+        }
+    }
+
+    let refs_updated: Vec<BTreeMap<&str, String>> = ref_updates
+        .iter()
+        .map(|(name, before_oid, after_oid)| {
+            BTreeMap::from([("ref", name.clone()), ("before", before_oid.to_string()), ("after", after_oid.to_string())])
+        })
+        .collect();
+    utils::set_output(
+        &github_output,
+        &args.output_prefix,
+        "refs_updated",
+        &utils::format_json_output(&serde_json::to_string(&refs_updated).unwrap(), &args.json_raw_format),
     );
 
-    let all_changed_and_modified_files = utils::get_diff(
+    if !args.dump_trees.is_empty() {
+        let dump_trees_dir = utils::get_workdir(&repo).join(&args.dump_trees);
+        if let Err(e) = std::fs::create_dir_all(&dump_trees_dir) {
+            println!("::error::Unable to create the dump_trees directory: {} ({})", dump_trees_dir.display(), e);
+            error::fail(FailureReason::OutputWriteFailed);
+        }
+
+        let tree_glob_patterns = if args.dump_trees_filtered { glob_patterns.clone() } else { Vec::new() };
+        for (key, commit, file_name) in [
+            ("base_tree_file", &previous_commit, "base_tree.jsonl"),
+            ("head_tree_file", &current_commit, "head_tree.jsonl"),
+        ] {
+            let file_path = dump_trees_dir.join(file_name);
+            utils::write_tree_listing(commit, &tree_glob_patterns, &file_path);
+            utils::set_output(&github_output, &args.output_prefix, key, &file_path.display().to_string());
+        }
+    }
+
+    if previous_commit.tree_id() == current_commit.tree_id() {
+        if args.fail_on_no_changes {
+            println!(
+                "::error::No changes detected: the previous commit ({}) and the current commit ({}) have identical trees.",
+                previous_commit.id(),
+                current_commit.id()
+            );
+            error::fail(FailureReason::NoChanges);
+        }
+
+        log!(
+            "::notice::The previous commit ({}) and the current commit ({}) have identical trees, skipping the diff.",
+            previous_commit.id(),
+            current_commit.id()
+        );
+        for (key, value) in utils::get_no_changes_outputs(&args) {
+            utils::set_output(&github_output, &args.output_prefix, &key, &value);
+        }
+        filters::write_filters_outputs(&filters, &utils::Diff::new(), &repo, &github_output, &args);
+        utils::set_output(&github_output, &args.output_prefix, "failed", "false");
+        report::write(&github_output, &args.output_prefix);
+        record::save();
+        temp::cleanup(false);
+        log!("::endgroup::");
+        std::process::exit(0);
+    }
+
+    // A single diff of every type, the lists of each type are derived from it
+    let diff_settings = utils::DiffSettings::from_args(&args);
+
+    let diff_timer = utils::Timer::start();
+    let mut all_changed_and_modified_files = utils::get_diff(
         &repo,
         &previous_commit,
         &current_commit,
@@ -231,18 +809,709 @@ fn main() {
         ],
         &diff,
         &glob_patterns,
+        &false,
+        &diff_settings,
+    );
+    let diff_duration = diff_timer.elapsed();
+
+    if all_changed_and_modified_files.deltas_limit_exceeded {
+        let deltas_count = all_changed_and_modified_files.deltas_count;
+        warn_or_fail!(
+            "The diff has {} deltas, more than max_deltas ({}). Only the counts are output, the file lists and every feature reading the changed files are skipped.",
+            deltas_count, args.max_deltas
+        );
+        utils::set_output(&github_output, &args.output_prefix, "any_changed", "true");
+        utils::set_output(&github_output, &args.output_prefix, "all_changed_and_modified_files_count", &deltas_count.to_string());
+        utils::set_output(&github_output, &args.output_prefix, "deltas_limit_exceeded", "true");
+        utils::set_output(&github_output, &args.output_prefix, "failed", "false");
+        report::write(&github_output, &args.output_prefix);
+        record::save();
+        temp::cleanup(false);
+        log!("::endgroup::");
+        std::process::exit(0);
+    }
+    utils::set_output(&github_output, &args.output_prefix, "deltas_limit_exceeded", "false");
+
+    // All changed files without applying the `files` and `files_ignore` filters, only needed for the workflow
+    // files, deleted directories, security sensitive files and the filter sets. Taken from the same diff
+    // before the files are excluded by the other inputs.
+    let needs_unfiltered_diff = !filters.is_empty()
+        || ["workflow_files_changed", "changed_workflow_files", "deleted_dirs", "security_sensitive_files_changed"]
+            .iter()
+            .any(|key| github_output::is_requested(key));
+    let unfiltered_changed_files_diff = if needs_unfiltered_diff {
+        all_changed_and_modified_files.with_other_files()
+    } else {
+        utils::Diff::new()
+    };
+
+    if args.exclude_root_level_files {
+        all_changed_and_modified_files.files.retain(|diff_file| diff_file.path.contains('/'));
+    }
+
+    if args.output_pattern_stats {
+        let mut pattern_match_counts: BTreeMap<String, usize> = BTreeMap::new();
+        for (pattern, count) in glob_patterns.iter().zip(&all_changed_and_modified_files.pattern_match_counts) {
+            *pattern_match_counts.entry(pattern.as_str().to_string()).or_default() += count;
+        }
+
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "pattern_match_counts",
+            &utils::format_json_output(&serde_json::to_string(&pattern_match_counts).unwrap(), &args.json_raw_format),
+        );
+    }
+
+    if args.exclude_generated_files {
+        // Attributes of the current commit, the working tree may be missing or differ
+        let attribute_rules = gitattributes::read_gitattributes(&repo, &current_commit, &args.generated_attribute_name);
+        let generated_changed_files: Vec<String> = all_changed_and_modified_files
+            .paths()
+            .into_iter()
+            .filter(|path| gitattributes::is_attribute_set(&attribute_rules, path))
+            .collect();
+
+        all_changed_and_modified_files.files.retain(|file| !generated_changed_files.contains(&file.path));
+
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "generated_changed_files",
+            &utils::format_files_output(&generated_changed_files, &args),
+        );
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "generated_changed_files_count",
+            &generated_changed_files.len().to_string(),
+        );
+    }
+
+    if args.min_size_delta_bytes > 0 {
+        let small_changed_files =
+            utils::remove_small_changes(&repo, &mut all_changed_and_modified_files, &args.min_size_delta_bytes);
+
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "small_changed_files",
+            &utils::format_files_output(&small_changed_files, &args),
+        );
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "small_changed_files_count",
+            &small_changed_files.len().to_string(),
+        );
+    }
+
+    if args.max_path_depth > 0 {
+        let files_count = all_changed_and_modified_files.files.len();
+        all_changed_and_modified_files
+            .files
+            .retain(|file| utils::get_path_depth(&file.path) <= args.max_path_depth);
+
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "deep_changed_files_count",
+            &(files_count - all_changed_and_modified_files.files.len()).to_string(),
+        );
+    }
+
+    if args.use_default_ignores {
+        log!("::debug::Applying the default ignore patterns (version {})", defaults::DEFAULT_IGNORES_VERSION);
+        defaults::apply_default_ignores(&mut all_changed_and_modified_files, &args.files_ignore, &args.files_ignore_separator);
+
+        let default_ignores: Vec<String> = defaults::DEFAULT_IGNORE_PATTERNS.iter().map(|pattern| pattern.to_string()).collect();
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "default_ignores_applied",
+            &utils::format_output_list(&default_ignores, &args.separator, &args.json, &args.json_raw_format),
+        );
+    }
+
+    if !co_change_rules.is_empty() {
+        let violations = co_changes::get_co_change_violations(&co_change_rules, &all_changed_and_modified_files.paths());
+        let annotation = if args.co_change_warn_only { "warning" } else { "error" };
+
+        for (rule, files) in &violations {
+            println!(
+                "::{}::Files matching the co-change rule ({}) changed without a matching change: {}",
+                annotation,
+                rule.describe(),
+                utils::quote_paths(files, &quotepath).join(", ")
+            );
+        }
+
+        let co_change_violations: Vec<serde_json::Value> = violations
+            .iter()
+            .map(|(rule, files)| {
+                serde_json::json!({ "if": rule.if_patterns, "then": rule.then_patterns, "files": utils::quote_paths(files, &quotepath) })
+            })
+            .collect();
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "co_change_violations",
+            &utils::format_json_output(&serde_json::to_string(&co_change_violations).unwrap(), &args.json_raw_format),
+        );
+
+        if !violations.is_empty() && !args.co_change_warn_only {
+            error::fail(FailureReason::CoChangeViolation);
+        }
+    }
+
+    if args.shard_count > 1 {
+        all_changed_and_modified_files.files.retain(|file| utils::is_in_shard(&file.path, &args.shard_index, &args.shard_count));
+        all_changed_and_modified_files.other_files.retain(|file| utils::is_in_shard(&file.path, &args.shard_index, &args.shard_count));
+
+        utils::set_output(&github_output, &args.output_prefix, "shard_index", &args.shard_index.to_string());
+        utils::set_output(&github_output, &args.output_prefix, "shard_count", &args.shard_count.to_string());
+    }
+
+    // After excluding the generated files so only the files in the outputs are described
+    if args.include_match_provenance {
+        let match_provenance = utils::get_match_provenance(&all_changed_and_modified_files.files, &quotepath);
+
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "match_provenance",
+            &utils::format_json_output(&serde_json::to_string(&match_provenance).unwrap(), &args.json_raw_format),
+        );
+    }
+
+    if args.ignore_filter_only_changes {
+        let filter_only_changed_files = utils::get_filter_only_changed_files(
+            &repo,
+            &previous_commit,
+            &current_commit,
+            &diff,
+            &all_changed_and_modified_files,
+        );
+
+        all_changed_and_modified_files.files.retain(|file| {
+            file.diff_type != DiffType::Modified || !filter_only_changed_files.contains(&file.path)
+        });
+
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "filter_only_changed_files",
+            &utils::format_files_output(&filter_only_changed_files, &args),
+        );
+    }
+
+    let added_files = all_changed_and_modified_files.of_types(&[DiffType::Added]);
+    let copied_files = all_changed_and_modified_files.of_types(&[DiffType::Copied]);
+    let deleted_files = all_changed_and_modified_files.of_types(&[DiffType::Deleted]);
+    let modified_files = all_changed_and_modified_files.of_types(&[DiffType::Modified]);
+    let renamed_files = all_changed_and_modified_files.of_types(&[DiffType::Renamed]);
+    // Renames without a content change and renames which also modified the content
+    let (moved_files, renamed_and_modified_files): (Vec<&utils::DiffFile>, Vec<&utils::DiffFile>) =
+        renamed_files.files.iter().partition(|file| file.is_move());
+    let type_changed_files = all_changed_and_modified_files.of_types(&[DiffType::TypeChanged]);
+    let mut unmerged_files = all_changed_and_modified_files.of_types(&[DiffType::Unmerged]);
+
+    // A tree diff has no conflicts, the files conflicting with the base are found with an in-memory merge
+    if args.detect_conflicts_with_base {
+        for path in utils::get_merge_conflicts(&repo, &previous_commit, &current_commit) {
+            if glob_patterns.is_empty() || glob_patterns.iter().any(|pattern| pattern.matches(&path)) {
+                log!("::debug::Conflicts with the base: {}", path);
+                let mut diff_file = utils::DiffFile::new();
+                diff_file.path = path.clone();
+                diff_file.previous_path = path;
+                diff_file.diff_type = DiffType::Unmerged;
+                unmerged_files.files.push(diff_file);
+            }
+        }
+    }
+    let unknown_files = all_changed_and_modified_files.of_types(&[DiffType::Unknown]);
+
+    if args.output_dir_stats {
+        let dir_stats = utils::get_dir_stats(&all_changed_and_modified_files.files, &args.dir_stats_depth);
+        let quoted_dir_stats: BTreeMap<String, BTreeMap<&str, usize>> = dir_stats
+            .into_iter()
+            .map(|(dir_name, counts)| (utils::quote_path(&dir_name, &quotepath), counts))
+            .collect();
+
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "dir_stats",
+            &utils::format_json_output(&serde_json::to_string(&quoted_dir_stats).unwrap(), &args.json_raw_format),
+        );
+    }
+
+    if all_changed_and_modified_files.files.is_empty() && args.fail_on_no_changes {
+        if all_changed_and_modified_files.deltas_count == 0 {
+            println!(
+                "::error::No changes detected: no files changed between {}{}{} e.g a reverted change",
+                previous_commit.id(),
+                diff,
+                current_commit.id()
+            );
+        } else {
+            println!(
+                "::error::No changes detected: {} files changed between {}{}{} but none of them matched the `files` patterns and filters",
+                all_changed_and_modified_files.deltas_count,
+                previous_commit.id(),
+                diff,
+                current_commit.id()
+            );
+        }
+        error::fail(FailureReason::NoChanges);
+    }
+
+    // Added, copied, modified and renamed files
+    let all_changed_files = all_changed_and_modified_files.paths_of_types(&utils::ALL_CHANGED_DIFF_TYPES);
+    // All changed files including the deleted files
+    let all_modified_files = all_changed_and_modified_files.paths_of_types(&utils::ALL_MODIFIED_DIFF_TYPES);
+
+    for (key, files) in [
+        ("added_files", added_files.paths()),
+        ("copied_files", copied_files.paths()),
+        ("deleted_files", deleted_files.paths()),
+        ("modified_files", modified_files.paths()),
+        ("renamed_files", renamed_files.paths()),
+        ("moved_files", moved_files.iter().map(|file| file.path.clone()).collect()),
+        ("renamed_and_modified_files", renamed_and_modified_files.iter().map(|file| file.path.clone()).collect()),
+        ("type_changed_files", type_changed_files.paths()),
+        ("unmerged_files", unmerged_files.paths()),
+        ("unknown_files", unknown_files.paths()),
+        ("all_changed_and_modified_files", all_changed_and_modified_files.paths()),
+        ("all_changed_files", all_changed_files.clone()),
+        ("all_modified_files", all_modified_files.clone()),
+    ] {
+        // Skip sorting and serializing the lists which weren't requested
+        if github_output::is_requested(key) {
+            utils::set_output(&github_output, &args.output_prefix, key, &utils::format_files_output(&files, &args));
+        }
+        utils::set_output(&github_output, &args.output_prefix, &format!("{}_count", key), &files.len().to_string());
+    }
+
+    if args.include_all_old_new_renamed_files {
+        let all_old_new_renamed_files: Vec<String> = renamed_files
+            .files
+            .iter()
+            .map(|file| format!("{}{}{}", file.previous_path, args.old_new_separator, file.path))
+            .collect();
+
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "all_old_new_renamed_files",
+            &all_old_new_renamed_files.join(&args.old_new_files_separator),
+        );
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "all_old_new_renamed_files_count",
+            &all_old_new_renamed_files.len().to_string(),
+        );
+    }
+
+    if github_output::is_requested("renamed_files_similarity") {
+        let renamed_files_similarity: BTreeMap<String, u16> = renamed_files
+            .files
+            .iter()
+            .map(|file| (utils::quote_path(&file.path, &quotepath), file.similarity))
+            .collect();
+
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "renamed_files_similarity",
+            &utils::format_json_output(&serde_json::to_string(&renamed_files_similarity).unwrap(), &args.json_raw_format),
+        );
+    }
+
+    // Changed files outside of the `files` patterns, the list is only written when it's small or explicitly requested
+    let other_changed_files = all_changed_and_modified_files.other_files.iter().map(|file| file.path.clone());
+    let other_changed_files_count = all_changed_and_modified_files.other_files.len();
+
+    utils::set_output(&github_output, &args.output_prefix, "any_other_changed", &(other_changed_files_count > 0).to_string());
+    utils::set_output(&github_output, &args.output_prefix, "other_changed_files_count", &other_changed_files_count.to_string());
+
+    if !github_output::is_requested("other_changed_files") {
+        // Not requested, skip building the list
+    } else if other_changed_files_count <= args.other_changed_files_max || github_output::is_explicitly_requested("other_changed_files") {
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "other_changed_files",
+            &utils::format_files_output(&other_changed_files.collect::<Vec<String>>(), &args),
+        );
+    } else {
+        log!(
+            "::debug::Skipping the other_changed_files output: {} files exceed other_changed_files_max ({}), request it with `outputs` to write it anyway",
+            other_changed_files_count, args.other_changed_files_max
+        );
+    }
+
+    utils::set_output(&github_output, &args.output_prefix, "any_changed", &(!all_changed_files.is_empty()).to_string());
+    utils::set_output(&github_output, &args.output_prefix, "any_modified", &(!all_modified_files.is_empty()).to_string());
+    utils::set_output(&github_output, &args.output_prefix, "any_deleted", &(!deleted_files.files.is_empty()).to_string());
+
+    if args.annotate_large_files_bytes > 0 {
+        let large_files = utils::get_large_files(
+            &repo,
+            &current_commit,
+            &all_changed_and_modified_files,
+            &args.annotate_large_files_bytes,
+        );
+
+        for (path, size) in &large_files {
+            log!(
+                "::notice file={}::Large file changed ({:.2} MB)",
+                path,
+                *size as f64 / (1024.0 * 1024.0)
+            );
+        }
+
+        let large_changed_files: Vec<String> = large_files.into_iter().map(|(path, _)| path).collect();
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "large_changed_files",
+            &utils::format_files_output(&large_changed_files, &args),
+        );
+    }
+
+    let unfiltered_changed_files = unfiltered_changed_files_diff.paths();
+
+    filters::write_filters_outputs(&filters, &unfiltered_changed_files_diff, &repo, &github_output, &args);
+
+    if github_output::is_requested("security_sensitive_files_changed") {
+        let security_sensitive_patterns = utils::get_security_sensitive_patterns(
+            &args.security_sensitive_patterns,
+            &args.no_builtin_sensitive_patterns,
+        );
+        // Without patterns every file would match
+        let security_sensitive_files_changed = !security_sensitive_patterns.is_empty()
+            && !unfiltered_changed_files_diff.matching(&security_sensitive_patterns, &utils::DiffSettings::default()).files.is_empty();
+
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "security_sensitive_files_changed",
+            &security_sensitive_files_changed.to_string(),
+        );
+    }
+
+    let changed_workflow_files = utils::get_workflow_files(&unfiltered_changed_files);
+
+    utils::set_output(
+        &github_output,
+        &args.output_prefix,
+        "workflow_files_changed",
+        &(!changed_workflow_files.is_empty()).to_string(),
+    );
+    utils::set_output(
+        &github_output,
+        &args.output_prefix,
+        "changed_workflow_files",
+        &utils::format_files_output(&changed_workflow_files, &args),
     );
 
+    for (key, commit) in [("base_sha", &previous_commit), ("sha", &current_commit)] {
+        let author = commit.author();
+
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            &format!("{}_timestamp", key),
+            &utils::format_timestamp_utc(commit.time().seconds()),
+        );
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            &format!("{}_author_name", key),
+            author.name().unwrap_or_default(),
+        );
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            &format!("{}_author_email", key),
+            author.email().unwrap_or_default(),
+        );
+    }
+
+    // Diffing every commit is expensive, shared by the per commit files and the churn
+    let per_commit_files = if args.per_commit_files || args.output_churn {
+        utils::get_per_commit_files(
+            &repo,
+            &previous_commit,
+            &current_commit,
+            &glob_patterns,
+            &args.exclude_root_level_files,
+            &args.max_commits,
+            &args.per_commit_full_merge_diff,
+            &diff_settings,
+        )
+    } else {
+        Vec::new()
+    };
+
+    if args.per_commit_files {
+        let per_commit_changed_files: Vec<serde_json::Value> = per_commit_files
+            .iter()
+            .map(|(sha, files)| serde_json::json!({ "sha": sha, "files": utils::quote_paths(files, &quotepath) }))
+            .collect();
+
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "per_commit_changed_files",
+            &utils::format_json_output(&serde_json::to_string(&per_commit_changed_files).unwrap(), &args.json_raw_format),
+        );
+    }
+
+    if args.output_churn {
+        let file_churn = utils::get_file_churn(&per_commit_files);
+        let high_churn_files: Vec<String> = file_churn
+            .iter()
+            .filter(|(_, count)| **count >= args.churn_threshold)
+            .map(|(path, _)| path.clone())
+            .collect();
+        let quoted_file_churn: BTreeMap<String, usize> = file_churn
+            .into_iter()
+            .map(|(path, count)| (utils::quote_path(&path, &quotepath), count))
+            .collect();
+
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "file_churn",
+            &utils::format_json_output(&serde_json::to_string(&quoted_file_churn).unwrap(), &args.json_raw_format),
+        );
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "high_churn_files",
+            &utils::format_files_output(&high_churn_files, &args),
+        );
+    }
+
+    if args.track_renames_through_history {
+        let rename_chains = match utils::get_rename_chains(&repo, &previous_commit, &current_commit, &args.max_commits) {
+            Some(rename_chains) => rename_chains,
+            None => {
+                warn_or_fail!(
+                    "The range contains more than {} commits, rename_chains only has the renames between the previous and the current commit. Increase `max_commits` to follow the renames through every commit.",
+                    args.max_commits
+                );
+                renamed_files
+                    .files
+                    .iter()
+                    .map(|file| (file.path.clone(), vec![file.previous_path.clone(), file.path.clone()]))
+                    .collect()
+            }
+        };
+
+        let quoted_rename_chains: BTreeMap<String, Vec<String>> = rename_chains
+            .into_iter()
+            .filter(|(path, _)| all_changed_and_modified_files.files.iter().any(|file| file.path == *path))
+            .map(|(path, rename_chain)| (utils::quote_path(&path, &quotepath), utils::quote_paths(&rename_chain, &quotepath)))
+            .collect();
 
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "rename_chains",
+            &utils::format_json_output(&serde_json::to_string(&quoted_rename_chains).unwrap(), &args.json_raw_format),
+        );
+    }
+
+    if args.submodule_config_changes {
+        let submodule_config_changes = gitmodules::get_submodule_config_changes(&repo, &previous_commit, &current_commit);
+
+        for (name, changes) in &submodule_config_changes {
+            log!("::debug::Submodule {} configuration changed: {}", name, changes);
+        }
+
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "submodule_config_changed",
+            &(!submodule_config_changes.is_empty()).to_string(),
+        );
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "submodule_config_changes",
+            &utils::format_json_output(&serde_json::to_string(&submodule_config_changes).unwrap(), &args.json_raw_format),
+        );
+    }
+
+    let unfiltered_deleted_files: Vec<String> = unfiltered_changed_files_diff
+        .files
+        .iter()
+        .filter(|diff_file| diff_file.diff_type == DiffType::Deleted)
+        .map(|diff_file| diff_file.path.clone())
+        .collect();
+
+    if github_output::is_requested("deleted_dirs") {
+        let deleted_dirs = utils::get_deleted_dirs(
+            &repo,
+            &previous_commit,
+            &deleted_files.paths(),
+            &unfiltered_deleted_files,
+            &args.dir_names_max_depth,
+        );
+
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "deleted_dirs",
+            &utils::format_output_list(&utils::quote_paths(&deleted_dirs, &quotepath), &args.separator, &args.json, &args.json_raw_format),
+        );
+    }
+
+    if args.recover_deleted_files {
+        let recovered_files = recover::recover_deleted_files(
+            &repo,
+            &previous_commit,
+            &deleted_files.paths(),
+            utils::get_workdir(&repo),
+            &args.recover_deleted_files_to_destination,
+        );
+        log!("Recovered {} deleted files", recovered_files.len());
+    }
+
+    // Grouped outputs only include the files that still exist in the current commit
+    let changed_files = all_changed_files.clone();
+
+    let case_only_renamed_files: Vec<String> = all_changed_and_modified_files
+        .files
+        .iter()
+        .filter(|diff_file| diff_file.is_case_only_rename())
+        .map(|diff_file| diff_file.path.clone())
+        .collect();
+
+    utils::set_output(
+        &github_output,
+        &args.output_prefix,
+        "case_only_renamed_files",
+        &utils::format_files_output(&case_only_renamed_files, &args),
+    );
+
+    if args.include_urls && github_output::is_requested("changed_files_urls") {
+        let server_url = std::env::var("GITHUB_SERVER_URL").unwrap_or_default();
+        let repository = std::env::var("GITHUB_REPOSITORY").unwrap_or_default();
+
+        if server_url.is_empty() || repository.is_empty() {
+            warn_or_fail!("`include_urls` requires GITHUB_SERVER_URL and GITHUB_REPOSITORY to be set, skipping the file URLs.");
+        } else {
+            let file_urls = utils::get_file_urls(
+                &server_url,
+                &repository,
+                &previous_commit.id().to_string(),
+                &current_commit.id().to_string(),
+                &diff,
+                &all_changed_and_modified_files.files,
+            );
+            let quoted_file_urls: BTreeMap<String, BTreeMap<&str, String>> = file_urls
+                .into_iter()
+                .map(|(path, urls)| (utils::quote_path(&path, &quotepath), urls))
+                .collect();
+
+            utils::set_output(
+                &github_output,
+                &args.output_prefix,
+                "changed_files_urls",
+                &utils::format_json_output(&serde_json::to_string(&quoted_file_urls).unwrap(), &args.json_raw_format),
+            );
+        }
+    }
+
+    if args.group_by_extension && (github_output::is_requested("changed_extensions") || github_output::is_requested("changed_files_by_extension")) {
+        let files_by_extension = utils::get_files_by_extension(&changed_files);
+        let changed_extensions: Vec<String> = files_by_extension.keys().cloned().collect();
+
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "changed_extensions",
+            &utils::format_output_list(&changed_extensions, &args.separator, &args.json, &args.json_raw_format),
+        );
+
+        if args.json {
+            utils::set_output(
+                &github_output,
+                &args.output_prefix,
+                "changed_files_by_extension",
+                &utils::format_json_output(
+                    &serde_json::to_string(&utils::quote_paths_map(&files_by_extension, &quotepath)).unwrap(),
+                    &args.json_raw_format,
+                ),
+            );
+        }
+    }
+
+    let needs_codeowners = ["owners", "unowned_changed_files", "owners_changed_files"]
+        .iter()
+        .any(|key| github_output::is_requested(key));
+
+    if !needs_codeowners {
+        log!("::debug::Skipping CODEOWNERS, none of its outputs were requested");
+    } else if let Some(codeowners_file) = codeowners::find_codeowners_file(utils::get_workdir(&repo), &args.codeowners) {
+        let codeowners_rules = codeowners::read_codeowners(&codeowners_file);
+        let (owners_changed_files, unowned_changed_files) =
+            codeowners::get_changed_files_by_owner(&codeowners_rules, &changed_files);
+        let owners: Vec<String> = owners_changed_files.keys().cloned().collect();
+
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "owners",
+            &utils::format_output_list(&owners, &args.separator, &args.json, &args.json_raw_format),
+        );
+        utils::set_output(
+            &github_output,
+            &args.output_prefix,
+            "unowned_changed_files",
+            &utils::format_files_output(&unowned_changed_files, &args),
+        );
+
+        if args.json {
+            utils::set_output(
+                &github_output,
+                &args.output_prefix,
+                "owners_changed_files",
+                &utils::format_json_output(
+                    &serde_json::to_string(&utils::quote_paths_map(&owners_changed_files, &quotepath)).unwrap(),
+                    &args.json_raw_format,
+                ),
+            );
+        }
+    } else {
+        log!("::debug::No CODEOWNERS file found");
+    }
+
+    let timings = [
+        ("fetch_duration_ms", utils::get_fetch_duration()),
+        ("sha_resolution_duration_ms", sha_resolution_duration),
+        ("diff_duration_ms", diff_duration),
+        ("output_write_duration_ms", utils::get_output_write_duration()),
+        ("total_duration_ms", total_timer.elapsed()),
+    ];
+
+    for (key, duration) in timings {
+        log!("::debug::{}: {}", key, duration.as_millis());
+
+        if args.output_timings {
+            utils::set_output(&github_output, &args.output_prefix, key, &duration.as_millis().to_string());
+        }
+    }
 
-    // writer::write_outputs(
-    //     &args.skip_missing_keys,
-    //     &keys,
-    //     &args.outputs,
-    //     &output_directory,
-    //     &args.extension,
-    //     &args.verbose,
-    // );
+    utils::set_output(&github_output, &args.output_prefix, "failed", "false");
+    report::write(&github_output, &args.output_prefix);
+    record::save();
+    temp::cleanup(false);
 
-    println!("::endgroup::");
+    log!("::endgroup::");
 }
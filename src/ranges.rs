@@ -0,0 +1,136 @@
+use std::collections::HashSet;
+
+use git2::{Commit, Repository};
+use glob::Pattern;
+use serde::Deserialize;
+
+use crate::args::Args;
+use crate::error::{self, FailureReason};
+use crate::utils::{self, DiffType};
+
+// A named comparison range from the `ranges` input
+#[derive(Debug, Deserialize)]
+pub struct Range {
+    pub name: String,
+    pub base: String,
+    // Defaults to the current commit
+    #[serde(default)]
+    pub head: String,
+    // Defaults to the diff operator of the event
+    #[serde(default)]
+    pub diff_mode: String,
+}
+
+// Utility function to parse and validate the `ranges` input, a JSON list of `{name, base, head?, diff_mode?}`
+pub fn parse_ranges(ranges: &str) -> Vec<Range> {
+    if ranges.trim().is_empty() {
+        return Vec::new();
+    }
+
+    let parsed_ranges: Vec<Range> = match serde_json::from_str(ranges) {
+        Ok(parsed_ranges) => parsed_ranges,
+        Err(e) => {
+            error::invalid(&format!("Invalid ranges: {}", e), FailureReason::InvalidInput);
+            return Vec::new();
+        }
+    };
+
+    let mut names: HashSet<&str> = HashSet::new();
+
+    for range in &parsed_ranges {
+        // The name is used as an output prefix
+        if !utils::is_valid_output_prefix(&range.name) || range.name.is_empty() {
+            error::invalid(
+                &format!(
+                    "Invalid range name: '{}'. Only letters, digits and underscores are allowed and it must not start with a digit",
+                    range.name
+                ),
+                FailureReason::InvalidInput,
+            );
+        }
+
+        if !names.insert(&range.name) {
+            error::invalid(&format!("Duplicate range name: '{}'", range.name), FailureReason::InvalidInput);
+        }
+
+        if range.base.is_empty() {
+            error::invalid(&format!("Missing base for range: '{}'", range.name), FailureReason::InvalidInput);
+        }
+
+        if !["", "..", "..."].contains(&range.diff_mode.as_str()) {
+            error::invalid(
+                &format!("Invalid diff_mode for range '{}': '{}'. Use '..' or '...'", range.name, range.diff_mode),
+                FailureReason::InvalidInput,
+            );
+        }
+    }
+
+    parsed_ranges
+}
+
+// Utility function to diff every range and write its outputs prefixed with `<name>_`.
+// The repository and the fetched history are shared, refs are only fetched when missing.
+#[allow(clippy::too_many_arguments)]
+pub fn write_ranges_outputs(
+    repo: &Repository,
+    ranges: &[Range],
+    current_commit: &Commit,
+    diff: &str,
+    glob_patterns: &[Pattern],
+    fetch_depth: &u32,
+    github_output: &str,
+    args: &Args,
+) {
+    for range in ranges {
+        log!("::debug::Resolving range {}: {}..{}", range.name, range.base, range.head);
+
+        let base_commit = utils::get_range_commit(&range.base, fetch_depth, &args.token, repo);
+        let head_commit = if range.head.is_empty() {
+            current_commit.clone()
+        } else {
+            utils::get_range_commit(&range.head, fetch_depth, &args.token, repo)
+        };
+        let range_diff = if range.diff_mode.is_empty() { diff } else { &range.diff_mode };
+
+        let changed_files = utils::get_diff(
+            repo,
+            &base_commit,
+            &head_commit,
+            &[
+                DiffType::Added,
+                DiffType::Copied,
+                DiffType::Deleted,
+                DiffType::Modified,
+                DiffType::Renamed,
+                DiffType::TypeChanged,
+                DiffType::Unmerged,
+                DiffType::Unknown
+            ],
+            range_diff,
+            glob_patterns,
+            &args.exclude_root_level_files,
+            &utils::DiffSettings::from_args(args),
+        );
+
+        let output_prefix = format!("{}{}_", args.output_prefix, range.name);
+        if changed_files.deltas_limit_exceeded {
+            warn_or_fail!(
+                "Range {} has {} deltas, more than max_deltas ({}). Only its counts are output.",
+                range.name, changed_files.deltas_count, args.max_deltas
+            );
+            utils::set_output(github_output, &output_prefix, "any_changed", "true");
+            utils::set_output(github_output, &output_prefix, "deltas_limit_exceeded", "true");
+            continue;
+        }
+        let all_changed_files = changed_files.paths_of_types(&utils::ALL_CHANGED_DIFF_TYPES);
+        let all_modified_files = changed_files.paths_of_types(&utils::ALL_MODIFIED_DIFF_TYPES);
+
+        utils::set_output(github_output, &output_prefix, "any_changed", &(!all_changed_files.is_empty()).to_string());
+        utils::set_output(github_output, &output_prefix, "all_changed_files", &utils::format_files_output(&all_changed_files, args));
+        utils::set_output(github_output, &output_prefix, "all_modified_files", &utils::format_files_output(&all_modified_files, args));
+        utils::set_output(github_output, &output_prefix, "added_files", &utils::format_files_output(&changed_files.paths_of_types(&[DiffType::Added]), args));
+        utils::set_output(github_output, &output_prefix, "deleted_files", &utils::format_files_output(&changed_files.paths_of_types(&[DiffType::Deleted]), args));
+        utils::set_output(github_output, &output_prefix, "modified_files", &utils::format_files_output(&changed_files.paths_of_types(&[DiffType::Modified]), args));
+        utils::set_output(github_output, &output_prefix, "renamed_files", &utils::format_files_output(&changed_files.paths_of_types(&[DiffType::Renamed]), args));
+    }
+}
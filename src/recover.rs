@@ -0,0 +1,212 @@
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use git2::{Commit, Repository};
+
+use crate::error::{self, FailureReason};
+use crate::utils;
+
+// Git file mode used for symbolic links
+const SYMLINK_FILE_MODE: i32 = 0o120000;
+
+// Git file mode used for executable files
+const EXECUTABLE_FILE_MODE: i32 = 0o100755;
+
+// Utility function to restore the deleted files from the previous commit into the workspace or
+// the `destination` directory. A file is never written outside of the destination: paths whose
+// parent directories are symlinks or that already exist as a symlink are refused with a warning.
+pub fn recover_deleted_files(
+    repo: &Repository,
+    previous_commit: &Commit,
+    deleted_files: &[String],
+    workspace: &Path,
+    destination: &str,
+) -> Vec<String> {
+    let destination_root = if destination.is_empty() {
+        workspace.to_path_buf()
+    } else {
+        workspace.join(destination)
+    };
+
+    if let Err(e) = fs::create_dir_all(&destination_root) {
+        println!("::error::Unable to create the destination directory: {} ({})", destination_root.display(), e);
+        error::fail(FailureReason::RecoveryFailed);
+    }
+
+    let destination_root = fs::canonicalize(&destination_root).unwrap();
+    let previous_tree = previous_commit.tree().unwrap();
+    let mut recovered_files: Vec<String> = Vec::new();
+
+    for path in deleted_files {
+        let entry = match previous_tree.get_path(Path::new(&utils::get_repository_path(path))) {
+            Ok(entry) => entry,
+            Err(_) => {
+                warn_or_fail!("Unable to recover {}: not found in the previous commit", path);
+                continue;
+            }
+        };
+
+        let blob = match entry.to_object(repo).and_then(|object| object.peel_to_blob()) {
+            Ok(blob) => blob,
+            Err(_) => {
+                log!("::debug::Skipping recovery of {}: not a file", path);
+                continue;
+            }
+        };
+
+        let target_path = match get_recovery_path(&destination_root, path) {
+            Ok(target_path) => target_path,
+            Err(reason) => {
+                warn_or_fail!("Refusing to recover {}: {}", path, reason);
+                continue;
+            }
+        };
+
+        let result = if entry.filemode() == SYMLINK_FILE_MODE {
+            create_symlink(blob.content(), &target_path)
+        } else {
+            write_file(blob.content(), entry.filemode() == EXECUTABLE_FILE_MODE, &target_path)
+        };
+
+        match result {
+            Ok(_) => {
+                log!("::debug::Recovered {} to {}", path, target_path.display());
+                recovered_files.push(path.clone());
+            }
+            Err(e) => warn_or_fail!("Unable to recover {}: {}", path, e),
+        }
+    }
+
+    recovered_files
+}
+
+// Utility function to resolve the path a deleted file is recovered to, creating the parent
+// directories. Fails when the path would escape `destination_root` or pass through a symlink.
+fn get_recovery_path(destination_root: &Path, path: &str) -> Result<PathBuf, String> {
+    let relative_path = Path::new(path);
+
+    if relative_path.components().any(|component| !matches!(component, Component::Normal(_))) {
+        return Err("the path is not a plain relative path".to_string());
+    }
+
+    let mut current_path = destination_root.to_path_buf();
+    let components: Vec<Component> = relative_path.components().collect();
+
+    for (index, component) in components.iter().enumerate() {
+        current_path.push(component);
+        let is_last = index == components.len() - 1;
+
+        match fs::symlink_metadata(&current_path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                return Err(format!("{} is a symlink", current_path.display()));
+            }
+            Ok(metadata) if !is_last && !metadata.is_dir() => {
+                return Err(format!("{} is not a directory", current_path.display()));
+            }
+            Ok(_) => {}
+            Err(_) if !is_last => {
+                fs::create_dir(&current_path).map_err(|e| e.to_string())?;
+            }
+            Err(_) => {}
+        }
+    }
+
+    // Guard against anything that changed the directories while they were created
+    let parent = fs::canonicalize(current_path.parent().unwrap()).map_err(|e| e.to_string())?;
+    if !parent.starts_with(destination_root) {
+        return Err(format!("{} is outside of {}", parent.display(), destination_root.display()));
+    }
+
+    Ok(current_path)
+}
+
+fn write_file(content: &[u8], is_executable: bool, target_path: &Path) -> io::Result<()> {
+    fs::write(target_path, content)?;
+
+    #[cfg(unix)]
+    if is_executable {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(target_path, fs::Permissions::from_mode(0o755))?;
+    }
+
+    #[cfg(not(unix))]
+    let _ = is_executable;
+
+    Ok(())
+}
+
+// Deleted symlinks are recreated as symlinks, the blob content is the link target
+fn create_symlink(content: &[u8], target_path: &Path) -> io::Result<()> {
+    let link_target = String::from_utf8_lossy(content).to_string();
+
+    if fs::symlink_metadata(target_path).is_ok() {
+        fs::remove_file(target_path)?;
+    }
+
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(link_target, target_path);
+
+    #[cfg(not(unix))]
+    return fs::write(target_path, link_target);
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::test_repo::{self, TestRepo};
+
+    #[test]
+    fn recovery_paths_never_leave_the_destination() {
+        let test_repo = TestRepo::new();
+        let destination_root = fs::canonicalize(&test_repo.dir).unwrap();
+        let outside_dir = destination_root.join("../").canonicalize().unwrap().join(format!("outside-{}", std::process::id()));
+        fs::create_dir_all(&outside_dir).unwrap();
+        std::os::unix::fs::symlink(&outside_dir, destination_root.join("escape")).unwrap();
+        std::os::unix::fs::symlink(outside_dir.join("target.txt"), destination_root.join("link.txt")).unwrap();
+
+        assert!(get_recovery_path(&destination_root, "../outside.txt").is_err());
+        assert!(get_recovery_path(&destination_root, "/etc/passwd").is_err());
+        assert_eq!(
+            get_recovery_path(&destination_root, "escape/nested/file.txt"),
+            Err(format!("{} is a symlink", destination_root.join("escape").display()))
+        );
+        assert!(get_recovery_path(&destination_root, "link.txt").is_err());
+        assert!(!outside_dir.join("nested").exists());
+
+        assert_eq!(get_recovery_path(&destination_root, "new/dir/file.txt"), Ok(destination_root.join("new/dir/file.txt")));
+        assert!(destination_root.join("new/dir").is_dir());
+
+        fs::remove_dir_all(&outside_dir).unwrap();
+    }
+
+    #[test]
+    fn deleted_files_are_recovered_without_following_symlinks() {
+        let _lock = test_repo::lock_global_settings();
+        let test_repo = TestRepo::new();
+        std::os::unix::fs::symlink("docs/guide.md", test_repo.dir.join("guide-link")).unwrap();
+        let mut index = test_repo.repo.index().unwrap();
+        index.add_path(Path::new("guide-link")).unwrap();
+        index.write().unwrap();
+        let previous_oid = test_repo.commit(&[("docs/guide.md", Some("guide\n")), ("config/secret.txt", Some("secret\n"))]);
+        test_repo.commit(&[("docs/guide.md", None), ("config/secret.txt", None)]);
+        fs::remove_file(test_repo.dir.join("guide-link")).unwrap();
+
+        // `config` is replaced by a symlink to a directory outside of the workspace
+        let outside_dir = test_repo.dir.join("../").canonicalize().unwrap().join(format!("outside-recovery-{}", std::process::id()));
+        fs::create_dir_all(&outside_dir).unwrap();
+        fs::remove_dir(test_repo.dir.join("config")).unwrap();
+        std::os::unix::fs::symlink(&outside_dir, test_repo.dir.join("config")).unwrap();
+
+        let deleted_files = ["docs/guide.md".to_string(), "config/secret.txt".to_string(), "guide-link".to_string()];
+        let recovered_files =
+            recover_deleted_files(&test_repo.repo, &test_repo.find_commit(previous_oid), &deleted_files, &test_repo.dir, "");
+
+        assert_eq!(recovered_files, vec!["docs/guide.md", "guide-link"]);
+        assert_eq!(fs::read_to_string(test_repo.dir.join("docs/guide.md")).unwrap(), "guide\n");
+        assert_eq!(fs::read_link(test_repo.dir.join("guide-link")).unwrap(), Path::new("docs/guide.md"));
+        assert!(!outside_dir.join("secret.txt").exists());
+
+        fs::remove_dir_all(&outside_dir).unwrap();
+    }
+}
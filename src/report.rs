@@ -0,0 +1,242 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::sync::Mutex;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{self, FailureReason};
+use crate::github_output;
+use crate::utils;
+
+// The single `result` output written with `single_output: json`, consumed with `fromJSON(steps.<id>.outputs.result)`.
+// Field names are part of the interface, renaming one breaks existing workflows.
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Report {
+    pub previous_sha: String,
+    pub current_sha: String,
+    pub diff: String,
+    #[serde(flatten)]
+    pub outputs: ReportOutputs,
+    // The outputs of each range and filter set by name
+    pub groups: BTreeMap<String, ReportOutputs>,
+}
+
+// The outputs of the run or a group, keyed by output name without the output prefix
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ReportOutputs {
+    // Boolean outputs e.g `any_changed`
+    pub flags: BTreeMap<String, bool>,
+    // `<list>_count` outputs
+    pub counts: BTreeMap<String, usize>,
+    // File list outputs as JSON lists
+    pub files: BTreeMap<String, serde_json::Value>,
+    // Every other output, JSON values are embedded as is
+    pub outputs: BTreeMap<String, serde_json::Value>,
+}
+
+// The report being collected, `None` unless `single_output` is `json`
+static REPORT: Mutex<Option<Report>> = Mutex::new(None);
+
+// The output prefix of the run, longer prefixes belong to a group
+static OUTPUT_PREFIX: Mutex<String> = Mutex::new(String::new());
+
+// Utility function to get the JSON Schema (draft-07) of the report. The groups are the `additionalProperties`
+// of the `groups` object as their names come from the ranges and filter sets.
+pub fn get_schema() -> String {
+    serde_json::to_string_pretty(&schemars::schema_for!(Report)).unwrap()
+}
+
+pub fn enable(output_prefix: &str) {
+    *REPORT.lock().unwrap() = Some(Report::default());
+    *OUTPUT_PREFIX.lock().unwrap() = output_prefix.to_string();
+}
+
+pub fn is_enabled() -> bool {
+    REPORT.lock().unwrap().is_some()
+}
+
+pub fn set_commits(previous_sha: &str, current_sha: &str, diff: &str) {
+    if let Some(report) = REPORT.lock().unwrap().as_mut() {
+        report.previous_sha = previous_sha.to_string();
+        report.current_sha = current_sha.to_string();
+        report.diff = diff.to_string();
+    }
+}
+
+// Utility function to add an output (internal key) to the report instead of writing it
+pub fn record(output_prefix: &str, key: &str, value: &str) {
+    let mut report = REPORT.lock().unwrap();
+    let report = match report.as_mut() {
+        Some(report) => report,
+        None => return,
+    };
+
+    let group = output_prefix
+        .strip_prefix(OUTPUT_PREFIX.lock().unwrap().as_str())
+        .and_then(|group| group.strip_suffix('_'))
+        .filter(|group| !group.is_empty());
+
+    let outputs = match group {
+        Some(group) => report.groups.entry(group.to_string()).or_default(),
+        None => &mut report.outputs,
+    };
+
+    let is_file_list = github_output::FILE_LIST_OUTPUT_KEYS.iter().any(|names| names.0 == key);
+    let key = github_output::get_output_name(key);
+
+    if let (Some(_), Ok(count)) = (key.strip_suffix("_count"), value.parse::<usize>()) {
+        outputs.counts.insert(key.to_string(), count);
+    } else if value == "true" || value == "false" {
+        outputs.flags.insert(key.to_string(), value == "true");
+    } else if is_file_list || value.starts_with('[') || value.starts_with('{') {
+        let json_value = serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+        if is_file_list {
+            outputs.files.insert(key.to_string(), json_value);
+        } else {
+            outputs.outputs.insert(key.to_string(), json_value);
+        }
+    } else {
+        outputs.outputs.insert(key.to_string(), serde_json::Value::String(value.to_string()));
+    }
+}
+
+// Utility function to write the collected report as the `result` output, later outputs are written as usual
+pub fn write(github_output: &str, output_prefix: &str) {
+    let report = match REPORT.lock().unwrap().take() {
+        Some(report) => report,
+        None => return,
+    };
+
+    utils::set_output(github_output, output_prefix, "result", &serde_json::to_string(&report).unwrap());
+}
+
+// The file lists of each change type, the status of a file is the list name without `_files`
+const STATUS_FILE_LISTS: [&str; 8] = [
+    "added_files",
+    "copied_files",
+    "deleted_files",
+    "modified_files",
+    "renamed_files",
+    "type_changed_files",
+    "unmerged_files",
+    "unknown_files",
+];
+
+// The differences between the changed files of two reports e.g before and after a rebase
+#[derive(Debug, Default, Serialize)]
+pub struct ReportComparison {
+    // Files only changed in the new report
+    pub added: Vec<String>,
+    // Files only changed in the old report
+    pub removed: Vec<String>,
+    // Files changed in both reports with a different status
+    pub status_changes: Vec<StatusChange>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatusChange {
+    pub path: String,
+    pub before: String,
+    pub after: String,
+}
+
+impl ReportComparison {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.status_changes.is_empty()
+    }
+}
+
+// Utility function to read a report saved from the `result` output
+pub fn read_report(file_path: &str) -> Report {
+    let contents = match fs::read_to_string(file_path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            println!("::error::Could not read the report: {} ({})", file_path, e);
+            error::fail(FailureReason::FileNotFound);
+        }
+    };
+
+    match serde_json::from_str(&contents) {
+        Ok(report) => report,
+        Err(e) => {
+            println!("::error::Invalid report: {} ({})", file_path, e);
+            error::fail(FailureReason::InvalidInput);
+        }
+    }
+}
+
+// Utility function to get the status of every changed file of a report e.g `modified`. Reports written
+// without the lists of each change type fall back to `all_changed_and_modified_files` with a `changed` status.
+// The lists are read by their node or native name, whichever naming the report was written with.
+fn get_file_statuses(report: &Report) -> BTreeMap<String, String> {
+    let mut file_statuses: BTreeMap<String, String> = BTreeMap::new();
+    let get_paths = |key: &str| -> Vec<String> {
+        let native_key = github_output::FILE_LIST_OUTPUT_KEYS
+            .iter()
+            .find(|names| names.0 == key)
+            .map_or(key, |names| names.1);
+
+        match report.outputs.files.get(key).or_else(|| report.outputs.files.get(native_key)) {
+            Some(serde_json::Value::Array(paths)) => paths.iter().filter_map(|path| path.as_str().map(String::from)).collect(),
+            _ => Vec::new(),
+        }
+    };
+
+    for key in STATUS_FILE_LISTS {
+        for path in get_paths(key) {
+            file_statuses.insert(path, key.trim_end_matches("_files").to_string());
+        }
+    }
+
+    if file_statuses.is_empty() {
+        for path in get_paths("all_changed_and_modified_files") {
+            file_statuses.insert(path, "changed".to_string());
+        }
+    }
+
+    file_statuses
+}
+
+// Utility function to compare the changed files of two reports, the groups aren't compared
+pub fn compare_reports(old_report: &Report, new_report: &Report) -> ReportComparison {
+    let old_statuses = get_file_statuses(old_report);
+    let new_statuses = get_file_statuses(new_report);
+    let paths: BTreeSet<&String> = old_statuses.keys().chain(new_statuses.keys()).collect();
+    let mut comparison = ReportComparison::default();
+
+    for path in paths {
+        match (old_statuses.get(path), new_statuses.get(path)) {
+            (None, Some(_)) => comparison.added.push(path.clone()),
+            (Some(_), None) => comparison.removed.push(path.clone()),
+            (Some(before), Some(after)) if before != after => comparison.status_changes.push(StatusChange {
+                path: path.clone(),
+                before: before.clone(),
+                after: after.clone(),
+            }),
+            _ => {}
+        }
+    }
+
+    comparison
+}
+
+// Utility function to format a comparison for the log, one line per file
+pub fn format_comparison(comparison: &ReportComparison) -> String {
+    if comparison.is_empty() {
+        return "The reports have the same changed files".to_string();
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    lines.extend(comparison.added.iter().map(|path| format!("+ {}", path)));
+    lines.extend(comparison.removed.iter().map(|path| format!("- {}", path)));
+    lines.extend(
+        comparison
+            .status_changes
+            .iter()
+            .map(|status_change| format!("~ {} ({} -> {})", status_change.path, status_change.before, status_change.after)),
+    );
+    lines.join("\n")
+}
@@ -0,0 +1,141 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::error::{self, FailureReason};
+
+// Process ids of the running git commands, each is the leader of its own process group
+static CHILD_PROCESSES: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+// Temporary files which are removed when the run is cancelled
+static TEMP_FILES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+// Set when a signal was received, the main thread fails the run at its next cancellation point
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+// How long the main thread has to reach a cancellation point before the process exits without writing the outputs
+const CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+// Utility function to handle SIGTERM and SIGINT (sent when a job is cancelled) on a background thread,
+// the signal handler itself only wakes the thread so the cleanup doesn't have to be async signal safe.
+// The thread only stops the git commands, the run is failed by the main thread so the outputs aren't
+// written by two threads at once.
+#[cfg(unix)]
+pub fn install_handler() {
+    use signal_hook::consts::{SIGINT, SIGTERM};
+    use signal_hook::iterator::Signals;
+
+    let mut signals = match Signals::new([SIGTERM, SIGINT]) {
+        Ok(signals) => signals,
+        Err(e) => {
+            println!("::warning::Unable to install the cancellation handler: {}", e);
+            return;
+        }
+    };
+
+    std::thread::spawn(move || {
+        if let Some(signal) = signals.forever().next() {
+            log!("::debug::Received signal {}", signal);
+            request_cancel();
+
+            // The main thread didn't reach a cancellation point e.g while diffing a large tree
+            std::thread::sleep(CANCEL_GRACE_PERIOD);
+            remove_temp_files();
+            println!("::warning::Run cancelled");
+            std::process::exit(FailureReason::Cancelled.exit_code());
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn install_handler() {}
+
+// Utility function to mark the run as cancelled and stop the running git commands. The flag is set with the
+// child processes locked so a command registered afterwards sees it and is stopped by `register_child`.
+fn request_cancel() {
+    let child_processes = CHILD_PROCESSES.lock().unwrap();
+    CANCELLED.store(true, Ordering::SeqCst);
+
+    for pid in child_processes.iter() {
+        kill_process_group(*pid);
+    }
+}
+
+// Utility function to fail the run on the main thread once a signal was received, called before writing an
+// output and after every git command. The flag is cleared so failing doesn't cancel again.
+pub fn check_cancelled() {
+    if CANCELLED.swap(false, Ordering::SeqCst) {
+        cancel();
+    }
+}
+
+// Utility function to remove the temporary files and fail the run
+fn cancel() -> ! {
+    remove_temp_files();
+
+    println!("::warning::Run cancelled");
+    error::fail(FailureReason::Cancelled);
+}
+
+fn remove_temp_files() {
+    for path in TEMP_FILES.lock().unwrap().iter() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[cfg(unix)]
+fn kill_process_group(pid: u32) {
+    // A negative pid signals the whole process group e.g `git fetch` and its `git remote-https` helper
+    unsafe {
+        libc::kill(-(pid as i32), libc::SIGTERM);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(_pid: u32) {}
+
+// Utility function to register a running git command, a command started after the cancellation is stopped right away
+pub fn register_child(pid: u32) {
+    let mut child_processes = CHILD_PROCESSES.lock().unwrap();
+    child_processes.push(pid);
+
+    if CANCELLED.load(Ordering::SeqCst) {
+        kill_process_group(pid);
+    }
+}
+
+pub fn unregister_child(pid: u32) {
+    CHILD_PROCESSES.lock().unwrap().retain(|child_pid| *child_pid != pid);
+}
+
+pub fn register_temp_file(path: &Path) {
+    TEMP_FILES.lock().unwrap().push(path.to_path_buf());
+}
+
+pub fn unregister_temp_file(path: &Path) {
+    TEMP_FILES.lock().unwrap().retain(|temp_file| temp_file != path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo;
+
+    #[test]
+    fn register_child_stops_a_command_started_after_the_cancellation() {
+        use std::os::unix::process::{CommandExt, ExitStatusExt};
+
+        let _lock = test_repo::lock_global_settings();
+        let mut child = std::process::Command::new("sleep").arg("30").process_group(0).spawn().unwrap();
+
+        request_cancel();
+        register_child(child.id());
+        let status = child.wait().unwrap();
+        unregister_child(child.id());
+        CANCELLED.store(false, Ordering::SeqCst);
+
+        assert_eq!(status.signal(), Some(libc::SIGTERM));
+        assert!(CHILD_PROCESSES.lock().unwrap().is_empty());
+    }
+}
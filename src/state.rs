@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::args::Args;
+use crate::utils;
+
+// Environment variables identifying the run, a change invalidates the state
+const FINGERPRINT_ENV_VARS: [&str; 3] = ["GITHUB_RUN_ID", "GITHUB_RUN_ATTEMPT", "GITHUB_EVENT_NAME"];
+
+// The event as resolved from the environment, the event payload and the inputs overriding them
+pub struct ResolvedEvent<'a> {
+    pub github_ref: &'a str,
+    pub github_refname: &'a str,
+    pub github_sha: &'a str,
+    pub github_event_base_ref: &'a str,
+    pub github_event_before: &'a str,
+    pub github_event_after: &'a str,
+    pub github_event_forced: bool,
+    pub github_event_pull_request_base_ref: &'a str,
+    pub github_event_pull_request_base_sha: &'a str,
+    pub github_event_pull_request_head_sha: &'a str,
+    // The current commit and whether HEAD is used once a checkout mismatch is handled
+    pub sha: &'a str,
+    pub use_head: bool,
+    // The default branch compared against with `base_default_branch`, empty otherwise
+    pub default_branch: &'a str,
+}
+
+// The resolved comparison of a previous invocation in the same job
+#[derive(Debug, Serialize, Deserialize)]
+pub struct State {
+    pub fingerprint: BTreeMap<String, String>,
+    pub previous_sha: String,
+    pub current_sha: String,
+    pub diff: String,
+    pub unrelated_histories: bool,
+    // Whether the repository was a shallow clone once the commits were resolved
+    pub is_shallow: bool,
+}
+
+// Utility function to get the path of the state file, defaults to `<output_dir>/.changes-state.json` with
+// `write_output_files`. Returns `None` when the state isn't persisted.
+pub fn get_state_file(args: &Args, repo: &Repository) -> Option<PathBuf> {
+    if !args.state_file.is_empty() {
+        Some(utils::get_workdir(repo).join(&args.state_file))
+    } else if args.write_output_files {
+        Some(utils::get_workdir(repo).join(&args.output_dir).join(".changes-state.json"))
+    } else {
+        None
+    }
+}
+
+// Utility function to fingerprint everything the resolution of the commits depends on: the resolved event, the
+// checked out HEAD and the inputs used to pick the commits
+pub fn get_fingerprint(args: &Args, event: &ResolvedEvent, repo: &Repository) -> BTreeMap<String, String> {
+    let mut fingerprint: BTreeMap<String, String> = FINGERPRINT_ENV_VARS
+        .iter()
+        .map(|name| (name.to_string(), std::env::var(name).unwrap_or_default()))
+        .collect();
+
+    // Every field of the payload can be used e.g the default branch or the fork of a pull request
+    let event_path = utils::get_event_path(&args.event_json);
+    let event_payload_digest: String = fs::read(&event_path)
+        .map(|payload| Sha256::digest(payload).iter().map(|byte| format!("{:02x}", byte)).collect())
+        .unwrap_or_default();
+    fingerprint.insert("event_path".to_string(), event_path);
+    fingerprint.insert("event_payload".to_string(), event_payload_digest);
+
+    let head_sha = repo.head().ok().and_then(|head| head.target()).map(|oid| oid.to_string()).unwrap_or_default();
+    fingerprint.insert("HEAD".to_string(), head_sha);
+
+    for (name, value) in [
+        ("ref", event.github_ref.to_string()),
+        ("ref_name", event.github_refname.to_string()),
+        ("event_sha", event.github_sha.to_string()),
+        ("event_base_ref", event.github_event_base_ref.to_string()),
+        ("before", event.github_event_before.to_string()),
+        ("after", event.github_event_after.to_string()),
+        ("forced", event.github_event_forced.to_string()),
+        ("pull_request_base_ref", event.github_event_pull_request_base_ref.to_string()),
+        ("pull_request_base_sha", event.github_event_pull_request_base_sha.to_string()),
+        ("pull_request_head_sha", event.github_event_pull_request_head_sha.to_string()),
+        ("sha", event.sha.to_string()),
+        ("base_sha", args.base_sha.clone()),
+        ("base_ref", args.base_ref.clone()),
+        ("since", args.since.clone()),
+        ("until", args.until.clone()),
+        ("between_tags", args.between_tags.clone()),
+        ("range", args.range.clone()),
+        ("pull_request_number", args.pull_request_number.clone()),
+        ("since_last_remote_commit", args.since_last_remote_commit.to_string()),
+        ("use_head", event.use_head.to_string()),
+        ("base_default_branch", args.base_default_branch.to_string()),
+        ("default_branch", event.default_branch.to_string()),
+        ("use_event_after", args.use_event_after.to_string()),
+        ("trust_checkout", args.trust_checkout.to_string()),
+    ] {
+        fingerprint.insert(name.to_string(), value);
+    }
+
+    fingerprint
+}
+
+// Utility function to read the state of a previous invocation. The state is only used when the fingerprint
+// is identical and both commits are still available, anything else resolves the commits again.
+pub fn read_state(state_file: &Option<PathBuf>, fingerprint: &BTreeMap<String, String>, repo: &Repository) -> Option<State> {
+    let state_file = state_file.as_ref()?;
+    let contents = fs::read_to_string(state_file).ok()?;
+
+    let state: State = match serde_json::from_str(&contents) {
+        Ok(state) => state,
+        Err(e) => {
+            log!("::debug::Ignoring the invalid state file {}: {}", state_file.display(), e);
+            return None;
+        }
+    };
+
+    if state.fingerprint != *fingerprint {
+        let changed: Vec<&String> = fingerprint
+            .iter()
+            .filter(|(name, value)| state.fingerprint.get(*name) != Some(value))
+            .map(|(name, _)| name)
+            .collect();
+        log!("::debug::Ignoring the state file, changed since it was written: {:?}", changed);
+        return None;
+    }
+
+    let is_commit_available =
+        |sha: &str| Oid::from_str(sha).map_or(false, |oid| repo.find_commit(oid).is_ok());
+
+    if !is_commit_available(&state.previous_sha) || !is_commit_available(&state.current_sha) {
+        log!("::debug::Ignoring the state file, the commits are no longer available");
+        return None;
+    }
+
+    // The merge base of a three dot diff may need history that was only fetched afterwards
+    if state.diff == "..." && (state.is_shallow != repo.is_shallow() || repo.merge_base(
+        Oid::from_str(&state.previous_sha).unwrap(),
+        Oid::from_str(&state.current_sha).unwrap(),
+    ).is_err()) {
+        log!("::debug::Ignoring the state file, the merge base is no longer available");
+        return None;
+    }
+
+    log!("::debug::Using the resolved commits from {}", state_file.display());
+
+    Some(state)
+}
+
+// Utility function to persist the resolved commits for the next invocation, failures only warn
+pub fn write_state(state_file: &Option<PathBuf>, state: &State) {
+    let state_file = match state_file {
+        Some(state_file) => state_file,
+        None => return,
+    };
+
+    let result = state_file
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(state_file, serde_json::to_string_pretty(state).unwrap()));
+
+    match result {
+        Ok(_) => log!("::debug::Wrote the resolved commits to {}", state_file.display()),
+        Err(e) => warn_or_fail!("Unable to write the state file {}: {}", state_file.display(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::{self, TestRepo};
+
+    fn event<'a>() -> ResolvedEvent<'a> {
+        ResolvedEvent {
+            github_ref: "refs/heads/main",
+            github_refname: "main",
+            github_sha: "2222222222222222222222222222222222222222",
+            github_event_base_ref: "",
+            github_event_before: "1111111111111111111111111111111111111111",
+            github_event_after: "2222222222222222222222222222222222222222",
+            github_event_forced: false,
+            github_event_pull_request_base_ref: "",
+            github_event_pull_request_base_sha: "",
+            github_event_pull_request_head_sha: "",
+            sha: "",
+            use_head: false,
+            default_branch: "",
+        }
+    }
+
+    #[test]
+    fn get_fingerprint_changes_with_the_resolved_event() {
+        let test_repo = TestRepo::new();
+        test_repo.commit(&[("a.txt", Some("a\n"))]);
+        let args = test_repo::parse_args(&[]);
+        let fingerprint = get_fingerprint(&args, &event(), &test_repo.repo);
+
+        assert_eq!(fingerprint, get_fingerprint(&args, &event(), &test_repo.repo));
+        assert_ne!(fingerprint, get_fingerprint(&test_repo::parse_args(&["--base-default-branch"]), &event(), &test_repo.repo));
+        assert_ne!(fingerprint, get_fingerprint(&test_repo::parse_args(&["--range", "origin/main...HEAD"]), &event(), &test_repo.repo));
+
+        for resolved_event in [
+            ResolvedEvent { github_ref: "refs/heads/feature", ..event() },
+            ResolvedEvent { github_refname: "feature", ..event() },
+            ResolvedEvent { github_event_before: "3333333333333333333333333333333333333333", ..event() },
+            ResolvedEvent { github_event_forced: true, ..event() },
+            ResolvedEvent { sha: "2222222222222222222222222222222222222222", ..event() },
+            ResolvedEvent { use_head: true, ..event() },
+            ResolvedEvent { default_branch: "main", ..event() },
+        ] {
+            assert_ne!(fingerprint, get_fingerprint(&args, &resolved_event, &test_repo.repo));
+        }
+    }
+
+    #[test]
+    fn get_fingerprint_changes_with_the_event_payload() {
+        let test_repo = TestRepo::new();
+        test_repo.commit(&[("a.txt", Some("a\n"))]);
+        let event_path = test_repo.dir.join("event.json");
+        let args = test_repo::parse_args(&["--event-json", event_path.to_str().unwrap()]);
+
+        fs::write(&event_path, r#"{"repository": {"default_branch": "main"}}"#).unwrap();
+        let fingerprint = get_fingerprint(&args, &event(), &test_repo.repo);
+
+        fs::write(&event_path, r#"{"repository": {"default_branch": "develop"}}"#).unwrap();
+        assert_ne!(fingerprint, get_fingerprint(&args, &event(), &test_repo.repo));
+    }
+}
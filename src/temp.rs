@@ -0,0 +1,65 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+// The directory of the scratch files of this run, created on first use
+static RUN_TEMP_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+// Whether the run directory was created and has to be cleaned up
+static IS_CREATED: AtomicBool = AtomicBool::new(false);
+
+// Keep the scratch files after a successful run
+static KEEP_TEMP: AtomicBool = AtomicBool::new(false);
+
+// Utility function to choose the directory of the scratch files: `temp_dir`, `RUNNER_TEMP` and then the
+// system temporary directory. Every run uses its own subdirectory.
+pub fn init(temp_dir: &str, keep_temp: &bool) {
+    let root = if !temp_dir.is_empty() {
+        PathBuf::from(temp_dir)
+    } else {
+        match std::env::var("RUNNER_TEMP") {
+            Ok(runner_temp) if !runner_temp.is_empty() => PathBuf::from(runner_temp),
+            _ => std::env::temp_dir(),
+        }
+    };
+
+    *RUN_TEMP_DIR.lock().unwrap() = Some(root.join(format!("changed-files-{}", std::process::id())));
+    KEEP_TEMP.store(*keep_temp, Ordering::Relaxed);
+}
+
+// Utility function to get the path of a scratch file in the run directory, creating the directory
+pub fn get_path(name: &str) -> std::io::Result<PathBuf> {
+    let run_temp_dir = RUN_TEMP_DIR
+        .lock()
+        .unwrap()
+        .get_or_insert_with(|| std::env::temp_dir().join(format!("changed-files-{}", std::process::id())))
+        .clone();
+
+    if !IS_CREATED.load(Ordering::Relaxed) {
+        fs::create_dir_all(&run_temp_dir)?;
+        IS_CREATED.store(true, Ordering::Relaxed);
+    }
+
+    Ok(run_temp_dir.join(name))
+}
+
+// Utility function to remove the run directory when the run exits, it's kept for debugging
+// when the run failed or `keep_temp` is set
+pub fn cleanup(failed: bool) {
+    if !IS_CREATED.swap(false, Ordering::Relaxed) {
+        return;
+    }
+
+    let run_temp_dir = match RUN_TEMP_DIR.lock().unwrap().clone() {
+        Some(run_temp_dir) => run_temp_dir,
+        None => return,
+    };
+
+    if failed || KEEP_TEMP.load(Ordering::Relaxed) {
+        log!("::notice::Kept the temporary files in {}", run_temp_dir.display());
+        return;
+    }
+
+    let _ = fs::remove_dir_all(&run_temp_dir);
+}
@@ -0,0 +1,100 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use clap::Parser;
+use git2::{Commit, Oid, Repository, Signature};
+
+use crate::args::Args;
+
+// Number of fixture repositories created by this test process, keeps their directories unique
+static REPO_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// Held by the tests changing the global settings e.g the requested outputs, they run one at a time
+static GLOBAL_SETTINGS: Mutex<()> = Mutex::new(());
+
+pub fn lock_global_settings() -> MutexGuard<'static, ()> {
+    GLOBAL_SETTINGS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// A repository in a temporary directory removed on drop, commits are made with libgit2 so git isn't needed
+pub struct TestRepo {
+    pub dir: PathBuf,
+    pub repo: Repository,
+}
+
+impl TestRepo {
+    pub fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "changed-files-test-{}-{}",
+            std::process::id(),
+            REPO_COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let repo = Repository::init(&dir).unwrap();
+        Self { dir, repo }
+    }
+
+    // Utility function to commit on top of HEAD, `Some(contents)` writes a file and `None` deletes it
+    pub fn commit(&self, changes: &[(&str, Option<&str>)]) -> Oid {
+        let mut index = self.repo.index().unwrap();
+
+        for (path, contents) in changes {
+            let file_path = self.dir.join(path);
+            match contents {
+                Some(contents) => {
+                    fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+                    fs::write(&file_path, contents).unwrap();
+                    index.add_path(Path::new(path)).unwrap();
+                }
+                None => {
+                    fs::remove_file(&file_path).unwrap();
+                    index.remove_path(Path::new(path)).unwrap();
+                }
+            }
+        }
+        index.write().unwrap();
+
+        let tree = self.repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let parent = self.repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&Commit> = parent.iter().collect();
+
+        self.repo.commit(Some("HEAD"), &signature, &signature, "Test commit", &tree, &parents).unwrap()
+    }
+
+    pub fn find_commit(&self, oid: Oid) -> Commit<'_> {
+        self.repo.find_commit(oid).unwrap()
+    }
+}
+
+impl Drop for TestRepo {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+// Utility function to parse the arguments of a run, the inputs without a default are empty unless given in `extra_args`
+pub fn parse_args(extra_args: &[&str]) -> Args {
+    let mut args: Vec<&str> = vec!["changed-files"];
+    for name in [
+        "--files",
+        "--files-from-source-file",
+        "--files-ignore",
+        "--files-ignore-from-source-file",
+        "--since",
+        "--until",
+        "--diff-relative",
+        "--dir-names-max-depth",
+    ] {
+        if !extra_args.contains(&name) {
+            args.extend([name, ""]);
+        }
+    }
+    args.extend(extra_args);
+
+    Args::parse_from(args)
+}
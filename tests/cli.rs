@@ -0,0 +1,308 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Number of fixtures created by this process, used to name their directories
+static FIXTURE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// The inputs without a default value, empty unless a test sets them
+const REQUIRED_ARGS: [&str; 8] = [
+    "--files",
+    "--files-from-source-file",
+    "--files-ignore",
+    "--files-ignore-from-source-file",
+    "--since",
+    "--until",
+    "--diff-relative",
+    "--dir-names-max-depth",
+];
+
+// A temporary directory holding a repository made with the git CLI and the files of a run, removed on drop
+struct Fixture {
+    dir: PathBuf,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "changed-files-cli-test-{}-{}",
+            std::process::id(),
+            FIXTURE_COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("repo")).unwrap();
+
+        let fixture = Self { dir };
+        fixture.git(&["init", "--quiet", "--initial-branch=main"]);
+        fixture
+    }
+
+    fn repo(&self) -> PathBuf {
+        self.dir.join("repo")
+    }
+
+    fn git(&self, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(self.repo())
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    // Write the files, `None` deletes a file, and commit them returning the SHA of the commit
+    fn commit(&self, files: &[(&str, Option<&str>)]) -> String {
+        for (path, contents) in files {
+            let path = self.repo().join(path);
+            match contents {
+                Some(contents) => {
+                    fs::create_dir_all(path.parent().unwrap()).unwrap();
+                    fs::write(&path, contents).unwrap();
+                }
+                None => fs::remove_file(&path).unwrap(),
+            }
+        }
+
+        self.git(&["add", "--all"]);
+        self.git(&["commit", "--quiet", "--allow-empty", "--message", "commit"]);
+        self.git(&["rev-parse", "HEAD"])
+    }
+
+    // Run the binary for a push event from `before` to `after` in the working directory, with the GitHub
+    // Actions environment of a run and an empty GITHUB_OUTPUT file
+    fn run_push(&self, current_dir: &Path, before: &str, after: &str, args: &[&str]) -> (Output, String) {
+        let event = format!(r#"{{"before": "{}", "after": "{}", "ref": "refs/heads/main"}}"#, before, after);
+        self.run(current_dir, "push", &event, after, "refs/heads/main", args)
+    }
+
+    // Run the binary for an event in the working directory, returning the process output and GITHUB_OUTPUT
+    fn run(&self, current_dir: &Path, event_name: &str, event: &str, github_sha: &str, github_ref: &str, args: &[&str]) -> (Output, String) {
+        let event_path = self.dir.join("event.json");
+        let github_output = self.dir.join("github_output");
+        fs::write(&event_path, event).unwrap();
+        fs::write(&github_output, "").unwrap();
+
+        let mut command = Command::new(env!("CARGO_BIN_EXE_changed_files"));
+        for name in REQUIRED_ARGS.iter().filter(|name| !args.contains(name)) {
+            command.args([name, ""]);
+        }
+        let output = command
+            .args(args)
+            .env_clear()
+            .env("PATH", std::env::var("PATH").unwrap_or_default())
+            .env("HOME", &self.dir)
+            .env("RUNNER_TEMP", &self.dir)
+            .env("GITHUB_ACTIONS", "true")
+            .env("GITHUB_EVENT_NAME", event_name)
+            .env("GITHUB_EVENT_PATH", &event_path)
+            .env("GITHUB_WORKSPACE", self.repo())
+            .env("GITHUB_OUTPUT", &github_output)
+            .env("GITHUB_SHA", github_sha)
+            .env("GITHUB_REF", github_ref)
+            .env("GITHUB_REF_NAME", github_ref.rsplit('/').next().unwrap())
+            .current_dir(current_dir)
+            .output()
+            .unwrap();
+
+        (output, fs::read_to_string(&github_output).unwrap())
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[test]
+fn quiet_run_prints_nothing_and_writes_every_output() {
+    let fixture = Fixture::new();
+    let before = fixture.commit(&[("README.md", Some("readme\n"))]);
+    let after = fixture.commit(&[("src/main.rs", Some("main\n"))]);
+
+    let (output, github_output) = fixture.run_push(&fixture.repo(), &before, &after, &["--quiet"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    assert!(github_output.contains("all_changed_files=src/main.rs\n"));
+    assert!(github_output.contains("any_changed=true\n"));
+
+    let (output, github_output_without_quiet) = fixture.run_push(&fixture.repo(), &before, &after, &[]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("::group::"));
+    assert_eq!(github_output_without_quiet.lines().count(), github_output.lines().count());
+}
+
+#[test]
+fn verbose_prints_the_debug_messages_and_conflicts_with_quiet() {
+    let fixture = Fixture::new();
+    let before = fixture.commit(&[("README.md", Some("readme\n"))]);
+    let after = fixture.commit(&[("src/main.rs", Some("main\n"))]);
+
+    let (output, _) = fixture.run_push(&fixture.repo(), &before, &after, &["--verbose"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(!stdout.contains("::debug::"));
+    assert!(stdout.lines().any(|line| line.starts_with("Base strategy: ")));
+
+    let (output, _) = fixture.run_push(&fixture.repo(), &before, &after, &["--quiet", "--verbose"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be used with"));
+}
+
+#[test]
+fn output_prefix_applies_to_every_output_sink() {
+    let fixture = Fixture::new();
+    let before = fixture.commit(&[("filters/docs.txt", Some("docs/**\n"))]);
+    let after = fixture.commit(&[("docs/guide.md", Some("guide\n")), ("src/main.rs", Some("main\n"))]);
+    let prefix_args = ["--output-prefix", "site_", "--outputs", "any_changed,all_changed_files,docs_any_changed"];
+
+    let (_, github_output) = fixture.run_push(&fixture.repo(), &before, &after, &prefix_args);
+    assert_eq!(github_output, "site_all_changed_files=docs/guide.md src/main.rs\nsite_any_changed=true\nsite_failed=false\n");
+
+    let (output, _) = fixture.run_push(&fixture.repo(), &before, &after, &[&prefix_args[..], &["--quiet", "--output-target", "stdout"]].concat());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "SITE_ALL_CHANGED_FILES=\"docs/guide.md src/main.rs\"\nSITE_ANY_CHANGED=\"true\"\nSITE_FAILED=\"false\"\n"
+    );
+
+    let dotenv_file = fixture.dir.join("outputs.env");
+    let dotenv_args = ["--output-target", "dotenv", "--output-dotenv-file", dotenv_file.to_str().unwrap()];
+    fixture.run_push(&fixture.repo(), &before, &after, &[&prefix_args[..], &dotenv_args].concat());
+    assert_eq!(
+        fs::read_to_string(&dotenv_file).unwrap(),
+        "SITE_ALL_CHANGED_FILES=\"docs/guide.md src/main.rs\"\nSITE_ANY_CHANGED=\"true\"\nSITE_FAILED=\"false\"\n"
+    );
+
+    // The values larger than `max_github_output_bytes` and the filter sets are written to `output_dir`
+    let output_dir = fixture.repo().join(".github/outputs");
+    let file_args = ["--max-github-output-bytes", "20", "--filters-from-files", "filters", "--write-output-files"];
+    let (_, github_output) = fixture.run_push(&fixture.repo(), &before, &after, &[&prefix_args[..], &file_args].concat());
+    assert_eq!(
+        github_output,
+        format!(
+            "site_all_changed_files_output_too_large=true\nsite_all_changed_files_file={}\nsite_any_changed=true\nsite_docs_any_changed=true\nsite_failed=false\n",
+            output_dir.join("site_all_changed_files.txt").display()
+        )
+    );
+    assert_eq!(fs::read_to_string(output_dir.join("site_all_changed_files.txt")).unwrap(), "docs/guide.md src/main.rs");
+    assert_eq!(fs::read_to_string(output_dir.join("docs/site_any_changed.txt")).unwrap(), "true");
+    assert!(!output_dir.join("docs/any_changed.txt").exists());
+}
+
+#[test]
+fn running_outside_of_the_repository_gives_the_same_outputs() {
+    let fixture = Fixture::new();
+    let base = fixture.commit(&[("README.md", Some("readme\n"))]);
+    fixture.git(&["tag", "v1"]);
+    fixture.git(&["update-ref", "refs/remotes/origin/main", &base]);
+    fixture.git(&["checkout", "--quiet", "-b", "feature"]);
+    let before = fixture.commit(&[("src/lib.rs", Some("lib\n"))]);
+    let head = fixture.commit(&[("src/main.rs", Some("main\n")), ("README.md", None)]);
+    fixture.git(&["tag", "--annotate", "--message", "v2", "v2"]);
+
+    // The working directory is a sibling of the repository, GITHUB_WORKSPACE points at the repository
+    let sibling_dir = fixture.dir.join("sibling");
+    fs::create_dir_all(&sibling_dir).unwrap();
+
+    let pull_request_event = format!(
+        r#"{{"pull_request": {{"number": 1, "base": {{"ref": "main", "sha": "{}"}}, "head": {{"ref": "feature", "sha": "{}"}}}}}}"#,
+        base, head
+    );
+    let scenarios = [
+        // A push compared with `before`
+        ("push", format!(r#"{{"before": "{}", "after": "{}", "ref": "refs/heads/feature"}}"#, before, head), "refs/heads/feature", "src/main.rs"),
+        // A pull request compared with its base branch
+        ("pull_request", pull_request_event, "refs/pull/1/merge", "src/lib.rs src/main.rs"),
+        // A tag compared with the previous tag
+        ("push", format!(r#"{{"before": "{}", "after": "{}", "ref": "refs/tags/v2"}}"#, "0".repeat(40), head), "refs/tags/v2", "src/lib.rs src/main.rs"),
+    ];
+
+    for (event_name, event, github_ref, all_changed_files) in &scenarios {
+        let mut outputs = Vec::new();
+
+        for current_dir in [fixture.repo(), sibling_dir.clone()] {
+            let (output, github_output) = fixture.run(&current_dir, event_name, event, &head, github_ref, &[]);
+            assert!(output.status.success(), "{} {}: {}", event_name, github_ref, String::from_utf8_lossy(&output.stdout));
+
+            // The durations differ between runs
+            let github_output: Vec<String> = github_output
+                .lines()
+                .filter(|line| !line.contains("_duration_ms="))
+                .map(|line| line.to_string())
+                .collect();
+            outputs.push(github_output);
+        }
+
+        assert_eq!(outputs[0], outputs[1], "{} {}", event_name, github_ref);
+        assert!(outputs[0].contains(&format!("all_changed_files={}", all_changed_files)), "{} {}: {:?}", event_name, github_ref, outputs[0]);
+    }
+}
+
+#[test]
+fn unborn_head_uses_the_fetched_commit() {
+    let upstream = Fixture::new();
+    let before = upstream.commit(&[("README.md", Some("readme\n"))]);
+    let after = upstream.commit(&[("src/main.rs", Some("main\n"))]);
+
+    // A checkout which only fetched the remote-tracking branch, HEAD still points to the unborn `main`
+    let fixture = Fixture::new();
+    fixture.git(&["fetch", "--quiet", upstream.repo().to_str().unwrap(), "main:refs/remotes/origin/main"]);
+    assert_eq!(fixture.git(&["symbolic-ref", "HEAD"]), "refs/heads/main");
+
+    let (output, github_output) = fixture.run_push(&fixture.repo(), &before, &after, &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains(&format!("::notice::HEAD points to the unborn branch refs/heads/main, using GITHUB_SHA ({})", after)), "{}", stdout);
+    assert!(github_output.contains("all_changed_files=src/main.rs\n"), "{}", github_output);
+
+    // A commit which wasn't fetched can't stand in for HEAD
+    let missing = "1".repeat(40);
+    let (output, _) = fixture.run_push(&fixture.repo(), &before, &missing, &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(stdout.contains("::error::HEAD points to the unborn branch refs/heads/main, the checkout produced no usable HEAD."), "{}", stdout);
+}
+
+#[test]
+fn lightweight_annotated_and_nested_tags_resolve_to_their_commits() {
+    let fixture = Fixture::new();
+    fixture.commit(&[("README.md", Some("readme\n"))]);
+    fixture.git(&["update-ref", "refs/remotes/origin/main", "HEAD"]);
+    let lightweight = fixture.commit(&[("a.txt", Some("a\n"))]);
+    fixture.git(&["tag", "v1"]);
+    let annotated = fixture.commit(&[("b.txt", Some("b\n"))]);
+    fixture.git(&["tag", "--annotate", "--message", "v2", "v2"]);
+    // A tag of a tag e.g a signed release of a tag
+    let nested = fixture.commit(&[("c.txt", Some("c\n"))]);
+    fixture.git(&["tag", "--annotate", "--message", "v3", "v3-inner"]);
+    fixture.git(&["tag", "--annotate", "--message", "v3", "v3", "v3-inner"]);
+    fixture.git(&["tag", "--delete", "v3-inner"]);
+    assert!(fixture.git(&["cat-file", "-p", "v3"]).contains("\ntype tag\n"));
+
+    // A tag push is compared with the previous tag, `after` is the pushed tag object for annotated tags
+    let tag_pushes = [("v2", &annotated, "b.txt"), ("v3", &nested, "c.txt")];
+    for (tag, commit, all_changed_files) in tag_pushes {
+        let tag_object = fixture.git(&["rev-parse", tag]);
+        let event = format!(r#"{{"before": "{}", "after": "{}", "ref": "refs/tags/{}"}}"#, "0".repeat(40), tag_object, tag);
+        let github_ref = format!("refs/tags/{}", tag);
+        let (output, github_output) = fixture.run(&fixture.repo(), "push", &event, commit, &github_ref, &[]);
+        assert!(output.status.success(), "{}: {}", tag, String::from_utf8_lossy(&output.stdout));
+        assert!(github_output.contains(&format!("all_changed_files={}\n", all_changed_files)), "{}: {}", tag, github_output);
+    }
+
+    // The inputs naming a tag are peeled to the commit
+    let explicit_inputs = [
+        (vec!["--base-sha", "v1", "--sha", "v3"], "b.txt c.txt"),
+        (vec!["--base-sha", "v2", "--sha", "v3"], "c.txt"),
+        (vec!["--base-ref", "v1", "--sha", "v2"], "b.txt"),
+        (vec!["--base-ref", "v3"], ""),
+    ];
+    for (args, all_changed_files) in explicit_inputs {
+        let (output, github_output) = fixture.run_push(&fixture.repo(), &lightweight, &nested, &args);
+        assert!(output.status.success(), "{:?}: {}", args, String::from_utf8_lossy(&output.stdout));
+        assert!(github_output.contains(&format!("all_changed_files={}\n", all_changed_files)), "{:?}: {}", args, github_output);
+    }
+}